@@ -0,0 +1,51 @@
+//! Where indexed files and blocks are stored and searched.
+
+pub mod memory;
+
+use crate::parsing::block::Block;
+use crate::parsing::file::File;
+
+/// A store that files and blocks can be indexed into and searched.
+///
+/// Filters use Meilisearch's syntax, e.g. `marker = TODO AND properties.type = book`.
+/// Backends other than Meilisearch may only support part of it.
+pub trait SearchBackend {
+    /// Get the backend ready to index into. Returns whether documents indexed before
+    /// were dropped, in which case every file has to be indexed again.
+    async fn migrate(&self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    /// Add files, replacing any with the same id.
+    async fn upsert_files(&self, files: &[File]) -> Result<(), String>;
+
+    /// Add blocks, replacing any with the same id.
+    async fn upsert_blocks(&self, blocks: &[Block]) -> Result<(), String>;
+
+    /// Remove files by id. Their blocks are removed separately with `delete_blocks`.
+    async fn delete_files(&self, file_ids: &[String]) -> Result<(), String>;
+
+    /// Remove every block belonging to the files with these ids.
+    async fn delete_blocks(&self, file_ids: &[String]) -> Result<(), String>;
+
+    async fn search_files(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<File>, String>;
+
+    async fn search_blocks(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Block>, String>;
+
+    async fn get_file(&self, id: &str) -> Result<Option<File>, String>;
+
+    /// Persist what was indexed, for backends that don't do so as they go.
+    async fn flush(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
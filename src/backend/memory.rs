@@ -0,0 +1,316 @@
+//! A search backend that keeps documents in memory, optionally saved to a JSON file, so a
+//! graph can be indexed and searched without a Meilisearch server.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::SearchBackend;
+use crate::meilisearch::SCHEMA_VERSION;
+use crate::parsing::block::Block;
+use crate::parsing::file::File;
+
+#[derive(Serialize, Deserialize, Default)]
+struct Documents {
+    /// The `SCHEMA_VERSION` the documents were indexed with
+    version: u32,
+    files: BTreeMap<String, File>,
+    blocks: BTreeMap<String, Block>,
+}
+
+/// One `field = value` or `field != value` comparison of a filter.
+#[derive(Debug, PartialEq)]
+struct Condition {
+    /// The field, split on dots, e.g. `["properties", "type"]`
+    field: Vec<String>,
+    value: String,
+    negate: bool,
+}
+
+impl Condition {
+    /// Parse a filter made of comparisons joined with `AND`, the part of Meilisearch's
+    /// filter syntax this backend supports.
+    fn parse_filter(filter: &str) -> Result<Vec<Condition>, String> {
+        let and = Regex::new(r"\s+AND\s+").unwrap();
+        let comparison = Regex::new(r#"^([A-Za-z0-9_.\-]+)\s*(!?=)\s*(.+)$"#).unwrap();
+        and.split(filter.trim())
+            .map(|part| {
+                let captures = comparison.captures(part.trim()).ok_or(format!(
+                    "Unsupported filter \"{}\", only `field = value` comparisons joined with AND are supported",
+                    filter
+                ))?;
+                let value = captures[3].trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .or(value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                    .unwrap_or(value);
+                Ok(Condition {
+                    field: captures[1].split('.').map(|key| key.to_string()).collect(),
+                    value: value.to_string(),
+                    negate: &captures[2] == "!=",
+                })
+            })
+            .collect()
+    }
+
+    /// Whether a field's value is, or contains, the expected value.
+    fn equals(value: &Value, expected: &str) -> bool {
+        match value {
+            Value::String(s) => s == expected,
+            Value::Number(n) => expected.parse::<f64>().ok() == n.as_f64(),
+            Value::Bool(b) => b.to_string() == expected,
+            Value::Array(items) => items.iter().any(|item| Self::equals(item, expected)),
+            // Page references are `{"page": "name"}`
+            Value::Object(object) => object
+                .get("page")
+                .is_some_and(|page| Self::equals(page, expected)),
+            Value::Null => false,
+        }
+    }
+
+    fn matches(&self, document: &Value) -> bool {
+        let found = self
+            .field
+            .iter()
+            .try_fold(document, |value, key| value.get(key))
+            .is_some_and(|value| Self::equals(value, &self.value));
+        found != self.negate
+    }
+}
+
+/// Whether every word of `query` is somewhere in `text`, ignoring case.
+fn matches_query(query: &str, text: &str) -> bool {
+    let text = text.to_lowercase();
+    query
+        .split_whitespace()
+        .all(|word| text.contains(&word.to_lowercase()))
+}
+
+/// Find documents matching a query and filter, in id order.
+fn search<T: Serialize + Clone>(
+    documents: &BTreeMap<String, T>,
+    text: impl Fn(&T) -> String,
+    query: &str,
+    filter: Option<&str>,
+    limit: usize,
+) -> Result<Vec<T>, String> {
+    let conditions = match filter {
+        Some(filter) => Condition::parse_filter(filter)?,
+        None => vec![],
+    };
+    let mut results = vec![];
+    for document in documents.values() {
+        if results.len() == limit {
+            break;
+        }
+        let value = serde_json::to_value(document).map_err(|e| e.to_string())?;
+        if matches_query(query, &text(document)) && conditions.iter().all(|c| c.matches(&value)) {
+            results.push(document.clone());
+        }
+    }
+    Ok(results)
+}
+
+pub struct MemoryBackend {
+    documents: Mutex<Documents>,
+    /// Where `flush` saves the documents
+    path: Option<PathBuf>,
+    /// Whether saved documents were dropped because they were indexed with another schema
+    migrated: bool,
+}
+
+impl MemoryBackend {
+    /// An empty backend that is never saved.
+    pub fn new() -> MemoryBackend {
+        MemoryBackend {
+            documents: Mutex::new(Documents {
+                version: SCHEMA_VERSION,
+                ..Documents::default()
+            }),
+            path: None,
+            migrated: false,
+        }
+    }
+
+    /// Where the local index of the graph at `root` is saved.
+    pub fn default_path(root: &Path) -> PathBuf {
+        root.join(".logseq-linter").join("local").join("index.json")
+    }
+
+    /// A backend saved at `path`, with whatever was saved there before.
+    pub fn open(path: &Path) -> Result<MemoryBackend, String> {
+        let mut backend = MemoryBackend::new();
+        backend.path = Some(path.to_path_buf());
+        let documents: Documents = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(backend),
+            Err(e) => return Err(e.to_string()),
+        };
+        if documents.version == SCHEMA_VERSION {
+            backend.documents = Mutex::new(documents);
+        } else {
+            backend.migrated = true;
+        }
+        Ok(backend)
+    }
+
+    fn file_text(file: &File) -> String {
+        let properties = file
+            .properties
+            .values()
+            .flat_map(|value| value.as_strings());
+        [file.title.clone(), file.path.clone()]
+            .into_iter()
+            .chain(file.aliases.iter().cloned())
+            .chain(file.tags.iter().cloned())
+            .chain(properties)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn block_text(block: &Block) -> String {
+        let properties = block
+            .properties
+            .values()
+            .flat_map(|value| value.as_strings());
+        std::iter::once(block.content.clone())
+            .chain(block.tags.iter().cloned())
+            .chain(properties)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl SearchBackend for MemoryBackend {
+    async fn migrate(&self) -> Result<bool, String> {
+        Ok(self.migrated)
+    }
+
+    async fn upsert_files(&self, files: &[File]) -> Result<(), String> {
+        let mut documents = self.documents.lock().unwrap();
+        for file in files {
+            documents.files.insert(file.id.clone(), file.clone());
+        }
+        Ok(())
+    }
+
+    async fn upsert_blocks(&self, blocks: &[Block]) -> Result<(), String> {
+        let mut documents = self.documents.lock().unwrap();
+        for block in blocks {
+            documents.blocks.insert(block.id.clone(), block.clone());
+        }
+        Ok(())
+    }
+
+    async fn delete_files(&self, file_ids: &[String]) -> Result<(), String> {
+        let mut documents = self.documents.lock().unwrap();
+        for file_id in file_ids {
+            documents.files.remove(file_id);
+        }
+        Ok(())
+    }
+
+    async fn delete_blocks(&self, file_ids: &[String]) -> Result<(), String> {
+        let mut documents = self.documents.lock().unwrap();
+        documents
+            .blocks
+            .retain(|_, block| !file_ids.contains(&block.file_id));
+        Ok(())
+    }
+
+    async fn search_files(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<File>, String> {
+        let documents = self.documents.lock().unwrap();
+        search(&documents.files, Self::file_text, query, filter, limit)
+    }
+
+    async fn search_blocks(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Block>, String> {
+        let documents = self.documents.lock().unwrap();
+        search(&documents.blocks, Self::block_text, query, filter, limit)
+    }
+
+    async fn get_file(&self, id: &str) -> Result<Option<File>, String> {
+        Ok(self.documents.lock().unwrap().files.get(id).cloned())
+    }
+
+    async fn flush(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let content =
+            serde_json::to_string(&*self.documents.lock().unwrap()).map_err(|e| e.to_string())?;
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(
+            Condition::parse_filter("marker = TODO AND properties.type != \"book\"").unwrap(),
+            vec![
+                Condition {
+                    field: vec!["marker".to_string()],
+                    value: "TODO".to_string(),
+                    negate: false,
+                },
+                Condition {
+                    field: vec!["properties".to_string(), "type".to_string()],
+                    value: "book".to_string(),
+                    negate: true,
+                },
+            ]
+        );
+        assert!(Condition::parse_filter("priority > 1").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let document = serde_json::json!({
+            "marker": "TODO",
+            "scheduled": 20240428,
+            "tags": ["a", "b"],
+            "properties": { "type": { "page": "book" } },
+        });
+        let matches = |filter: &str| {
+            Condition::parse_filter(filter)
+                .unwrap()
+                .iter()
+                .all(|c| c.matches(&document))
+        };
+        assert!(matches("marker = TODO"));
+        assert!(matches("scheduled = 20240428"));
+        assert!(matches("tags = b"));
+        assert!(matches("properties.type = book"));
+        assert!(matches("marker != DONE AND tags != c"));
+        assert!(!matches("properties.author = someone"));
+        assert!(matches("properties.author != someone"));
+    }
+
+    #[test]
+    fn test_matches_query() {
+        assert!(matches_query("", "anything"));
+        assert!(matches_query("lorem DOLOR", "Lorem ipsum dolor"));
+        assert!(!matches_query("lorem sit", "Lorem ipsum dolor"));
+    }
+}
@@ -0,0 +1,289 @@
+//! The graph's `logseq/config.edn`.
+
+pub mod edn;
+
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use edn::Value;
+
+use crate::parsing::file::File;
+use crate::parsing::journal;
+use crate::parsing::name::NameNormalizer;
+
+/// How namespaced page titles are written in file names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileNameFormat {
+    /// `a___b.md` for the page `a/b`
+    TripleLowbar,
+    /// `a.b.md` or `a%2Fb.md` for the page `a/b`, used before `:triple-lowbar`
+    Legacy,
+}
+
+/// Pages that the orphan and dead-end reports leave out. Logseq ignores the key they are
+/// set with, e.g. `:logseq-linter/gardening {:exclude-journals? false :allowlist ["inbox"]}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gardening {
+    /// `:exclude-contents?`, whether to leave out the contents page
+    pub exclude_contents: bool,
+    /// `:exclude-journals?`, whether to leave out journals
+    pub exclude_journals: bool,
+    /// `:allowlist`, normalized titles of pages that are never reported
+    pub allowlist: HashSet<String>,
+}
+
+impl Default for Gardening {
+    fn default() -> Gardening {
+        Gardening {
+            exclude_contents: true,
+            exclude_journals: true,
+            allowlist: HashSet::new(),
+        }
+    }
+}
+
+impl Gardening {
+    /// Whether a page is left out of the reports.
+    pub fn excludes(&self, file: &File) -> bool {
+        (self.exclude_contents && file.normalized_title == "contents")
+            || (self.exclude_journals && file.is_journal)
+            || self.allowlist.contains(&file.normalized_title)
+    }
+
+    fn parse(value: &Value, normalizer: NameNormalizer) -> Gardening {
+        let mut gardening = Gardening::default();
+        if let Some(Value::Bool(exclude)) = value.get("exclude-contents?") {
+            gardening.exclude_contents = *exclude;
+        }
+        if let Some(Value::Bool(exclude)) = value.get("exclude-journals?") {
+            gardening.exclude_journals = *exclude;
+        }
+        if let Some(items) = value.get("allowlist").and_then(|v| v.items()) {
+            gardening.allowlist = items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(title) => Some(normalizer.normalize(title)),
+                    _ => None,
+                })
+                .collect();
+        }
+        gardening
+    }
+}
+
+/// The parts of `logseq/config.edn` that change how the graph is read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphConfig {
+    /// `:file/name-format`
+    pub file_name_format: FileNameFormat,
+    /// `:hidden`, paths relative to the graph root that logseq ignores
+    pub hidden: Vec<String>,
+    /// `:journal/page-title-format`
+    pub journal_page_title_format: String,
+    /// `:journal/file-name-format`
+    pub journal_file_name_format: String,
+    /// `:journals-directory`
+    pub journals_directory: String,
+    /// `:property-pages/enabled?`, whether property keys get their own page
+    pub property_pages_enabled: bool,
+    /// `:block/content-max-length`
+    pub block_content_max_length: usize,
+    /// `:ignored-page-references-keywords`, property keys whose values are not page references
+    pub ignored_page_references_keywords: HashSet<String>,
+    /// `:property/separated-by-commas`, property keys whose values are comma separated lists
+    pub property_separated_by_commas: HashSet<String>,
+    /// `:feature/enable-search-remove-accents?`, whether page names are compared without accents
+    pub remove_accents: bool,
+    /// `:logseq-linter/gardening`
+    pub gardening: Gardening,
+}
+
+impl Default for GraphConfig {
+    /// Logseq's defaults, used for any key the config does not set. The file name
+    /// format defaults to `:triple-lowbar`, which every graph created by a recent
+    /// version of logseq sets explicitly.
+    fn default() -> GraphConfig {
+        GraphConfig {
+            file_name_format: FileNameFormat::TripleLowbar,
+            hidden: vec![],
+            journal_page_title_format: journal::DEFAULT_PAGE_TITLE_FORMAT.to_string(),
+            journal_file_name_format: journal::DEFAULT_FILE_NAME_FORMAT.to_string(),
+            journals_directory: "journals".to_string(),
+            property_pages_enabled: true,
+            block_content_max_length: 10000,
+            ignored_page_references_keywords: HashSet::new(),
+            property_separated_by_commas: HashSet::new(),
+            remove_accents: true,
+            gardening: Gardening::default(),
+        }
+    }
+}
+
+impl GraphConfig {
+    /// Load the config of the graph at `root`, or the defaults if it has none.
+    pub fn load(root: &Path) -> Result<GraphConfig, String> {
+        let path = root.join("logseq").join("config.edn");
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                Self::parse(&content).map_err(|e| format!("{}: {}", path.to_string_lossy(), e))
+            }
+            // A single file can be indexed too, it has no config
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+                Ok(GraphConfig::default())
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Read a config from the content of a `config.edn`.
+    pub fn parse(content: &str) -> Result<GraphConfig, String> {
+        let value = edn::parse(content)?;
+        let mut config = GraphConfig::default();
+        if let Some(Value::Keyword(format)) = value.get("file/name-format") {
+            config.file_name_format = match format.as_str() {
+                "triple-lowbar" => FileNameFormat::TripleLowbar,
+                "legacy" => FileNameFormat::Legacy,
+                _ => return Err(format!("Unknown :file/name-format :{}", format)),
+            };
+        }
+        if let Some(items) = value.get("hidden").and_then(|v| v.items()) {
+            config.hidden = items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(path) => Some(path.clone()),
+                    _ => None,
+                })
+                .collect();
+        }
+        if let Some(Value::String(format)) = value.get("journal/page-title-format") {
+            config.journal_page_title_format = format.clone();
+        }
+        if let Some(Value::String(format)) = value.get("journal/file-name-format") {
+            config.journal_file_name_format = format.clone();
+        }
+        if let Some(Value::String(directory)) = value.get("journals-directory") {
+            config.journals_directory = directory.clone();
+        }
+        if let Some(Value::Bool(enabled)) = value.get("property-pages/enabled?") {
+            config.property_pages_enabled = *enabled;
+        }
+        if let Some(Value::Integer(length)) = value.get("block/content-max-length") {
+            config.block_content_max_length = *length as usize;
+        }
+        if let Some(items) = value
+            .get("ignored-page-references-keywords")
+            .and_then(|v| v.items())
+        {
+            config.ignored_page_references_keywords = items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Keyword(keyword) => Some(keyword.clone()),
+                    _ => None,
+                })
+                .collect();
+        }
+        if let Some(items) = value
+            .get("property/separated-by-commas")
+            .and_then(|v| v.items())
+        {
+            config.property_separated_by_commas = items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Keyword(keyword) => Some(keyword.clone()),
+                    _ => None,
+                })
+                .collect();
+        }
+        if let Some(Value::Bool(remove)) = value.get("feature/enable-search-remove-accents?") {
+            config.remove_accents = *remove;
+        }
+        if let Some(gardening) = value.get("logseq-linter/gardening") {
+            config.gardening = Gardening::parse(gardening, config.normalizer());
+        }
+        Ok(config)
+    }
+
+    /// How page names are compared in this graph.
+    pub fn normalizer(&self) -> NameNormalizer {
+        NameNormalizer::new(self.remove_accents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_sample_config() {
+        let config = GraphConfig::load(Path::new("graph")).unwrap();
+        assert_eq!(config.file_name_format, FileNameFormat::TripleLowbar);
+        assert_eq!(config.hidden, Vec::<String>::new());
+        assert_eq!(config.journal_page_title_format, "MMM do, yyyy");
+        assert!(config.property_pages_enabled);
+        assert_eq!(config.block_content_max_length, 10000);
+        assert!(config.remove_accents);
+    }
+
+    #[test]
+    fn test_load_missing_config() {
+        let config = GraphConfig::load(Path::new("graph/pages")).unwrap();
+        assert_eq!(config, GraphConfig::default());
+    }
+
+    #[test]
+    fn test_parse() {
+        let config = GraphConfig::parse(
+            r#"{:hidden ["/archived" "/test.md"]
+                :journal/page-title-format "yyyy-MM-dd"
+                :journal/file-name-format "yyyy-MM-dd"
+                :journals-directory "daily"
+                :property-pages/enabled? false
+                :block/content-max-length 50
+                :feature/enable-search-remove-accents? false
+                :property/separated-by-commas #{:sizes}
+                :ignored-page-references-keywords #{:author :website}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.file_name_format, FileNameFormat::TripleLowbar);
+        assert_eq!(config.hidden, vec!["/archived", "/test.md"]);
+        assert_eq!(config.journal_page_title_format, "yyyy-MM-dd");
+        assert_eq!(config.journal_file_name_format, "yyyy-MM-dd");
+        assert_eq!(config.journals_directory, "daily");
+        assert!(!config.property_pages_enabled);
+        assert_eq!(config.block_content_max_length, 50);
+        assert!(!config.remove_accents);
+        assert_eq!(
+            config.ignored_page_references_keywords,
+            HashSet::from(["author".to_string(), "website".to_string()])
+        );
+        assert_eq!(
+            config.property_separated_by_commas,
+            HashSet::from(["sizes".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_gardening() {
+        let config = GraphConfig::parse(
+            r#"{:logseq-linter/gardening {:exclude-journals? false :allowlist [" Inbox  Zéro"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.gardening,
+            Gardening {
+                exclude_contents: true,
+                exclude_journals: false,
+                allowlist: HashSet::from(["inbox zero".to_string()]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_file_name_format() {
+        assert_eq!(
+            GraphConfig::parse("{:file/name-format :other}"),
+            Err("Unknown :file/name-format :other".to_string())
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! A small reader for the subset of EDN used by logseq's `config.edn`.
+//!
+//! Function bodies and queries in the config are read as plain lists and symbols,
+//! nothing is evaluated.
+
+/// An EDN value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Char(char),
+    /// A keyword without its leading `:`, e.g. `file/name-format`
+    Keyword(String),
+    Symbol(String),
+    List(Vec<Value>),
+    Vector(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    /// A tagged literal such as `#inst "..."`
+    Tagged(String, Box<Value>),
+}
+
+impl Value {
+    /// Look up a keyword in a map.
+    pub fn get(&self, keyword: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Value::Keyword(k) if k == keyword))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The items of a list, vector or set.
+    pub fn items(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) | Value::Vector(items) | Value::Set(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Characters that end a symbol, keyword or number.
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == ',' || "()[]{}\";".contains(c)
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.input[..self.offset].matches('\n').count() + 1;
+        format!("Invalid EDN on line {}: {}", line, message)
+    }
+
+    /// Skip whitespace, commas, `;` comments and `#_` discarded forms.
+    fn skip_ignored(&mut self) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() || c == ',' => {
+                    self.bump();
+                }
+                Some(';') => while !matches!(self.bump(), Some('\n') | None) {},
+                Some('#') if self.input[self.offset..].starts_with("#_") => {
+                    self.offset += 2;
+                    self.read()?;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Read a token up to the next delimiter.
+    fn token(&mut self) -> &'a str {
+        let start = self.offset;
+        while self.peek().is_some_and(|c| !is_delimiter(c)) {
+            self.bump();
+        }
+        &self.input[start..self.offset]
+    }
+
+    /// Read forms until `close`.
+    fn read_until(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut items = vec![];
+        loop {
+            self.skip_ignored()?;
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.bump();
+                    return Ok(items);
+                }
+                Some(_) => items.push(self.read()?),
+                None => return Err(self.error(&format!("expected '{}'", close))),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.bump() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    Some(c) => string.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn read_atom(&mut self) -> Result<Value, String> {
+        let token = self.token();
+        if token.is_empty() {
+            return Err(self.error(&format!("unexpected '{}'", self.peek().unwrap_or(' '))));
+        }
+        Ok(match token {
+            "nil" => Value::Nil,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => {
+                if let Some(keyword) = token.strip_prefix(':') {
+                    Value::Keyword(keyword.to_string())
+                } else if let Ok(i) = token.trim_end_matches('N').parse::<i64>() {
+                    Value::Integer(i)
+                } else if let Ok(f) = token.trim_end_matches('M').parse::<f64>() {
+                    Value::Float(f)
+                } else {
+                    Value::Symbol(token.to_string())
+                }
+            }
+        })
+    }
+
+    /// Read one form.
+    fn read(&mut self) -> Result<Value, String> {
+        self.skip_ignored()?;
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some('(') => {
+                self.bump();
+                Ok(Value::List(self.read_until(')')?))
+            }
+            Some('[') => {
+                self.bump();
+                Ok(Value::Vector(self.read_until(']')?))
+            }
+            Some('{') => {
+                self.bump();
+                let items = self.read_until('}')?;
+                if items.len() % 2 != 0 {
+                    return Err(self.error("map with an odd number of forms"));
+                }
+                let mut entries = vec![];
+                let mut items = items.into_iter();
+                while let (Some(k), Some(v)) = (items.next(), items.next()) {
+                    entries.push((k, v));
+                }
+                Ok(Value::Map(entries))
+            }
+            Some('"') => {
+                self.bump();
+                Ok(Value::String(self.read_string()?))
+            }
+            Some('\\') => {
+                self.bump();
+                let c = self.bump().ok_or(self.error("unterminated character"))?;
+                let rest = self.token();
+                Ok(Value::Char(match (c, rest) {
+                    (_, "") => c,
+                    ('n', "ewline") => '\n',
+                    ('s', "pace") => ' ',
+                    ('t', "ab") => '\t',
+                    _ => return Err(self.error("unknown character")),
+                }))
+            }
+            // Quoting and dereferencing don't matter when nothing is evaluated
+            Some('\'') | Some('@') => {
+                self.bump();
+                self.read()
+            }
+            Some('^') => {
+                // Metadata applies to the next form, which is what we want
+                self.bump();
+                self.read()?;
+                self.read()
+            }
+            Some('#') => {
+                self.bump();
+                match self.peek() {
+                    Some('{') => {
+                        self.bump();
+                        Ok(Value::Set(self.read_until('}')?))
+                    }
+                    // Anonymous functions, #(...)
+                    Some('(') => self.read(),
+                    // Regular expressions, #"..."
+                    Some('"') => {
+                        self.bump();
+                        Ok(Value::String(self.read_string()?))
+                    }
+                    _ => {
+                        let tag = self.token().to_string();
+                        Ok(Value::Tagged(tag, Box::new(self.read()?)))
+                    }
+                }
+            }
+            Some(')') | Some(']') | Some('}') => Err(self.error("unmatched closing delimiter")),
+            Some(_) => self.read_atom(),
+        }
+    }
+}
+
+/// Read a single EDN form.
+pub fn parse(input: &str) -> Result<Value, String> {
+    let mut reader = Reader { input, offset: 0 };
+    let value = reader.read()?;
+    reader.skip_ignored()?;
+    if reader.peek().is_some() {
+        return Err(reader.error("unexpected input after the first form"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = parse(
+            r#"{:a 1, :b/c? true ; comment
+                :d [nil "s\"tr" \x] #_ :discarded
+                :e #{:x :y} :f (fn [r] (sort-by :p r)) :g -1.5}"#,
+        )
+        .unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Integer(1)));
+        assert_eq!(value.get("b/c?"), Some(&Value::Bool(true)));
+        assert_eq!(
+            value.get("d"),
+            Some(&Value::Vector(vec![
+                Value::Nil,
+                Value::String("s\"tr".to_string()),
+                Value::Char('x')
+            ]))
+        );
+        assert_eq!(
+            value.get("e").and_then(|e| e.items()),
+            Some(
+                &[
+                    Value::Keyword("x".to_string()),
+                    Value::Keyword("y".to_string())
+                ][..]
+            )
+        );
+        assert!(matches!(value.get("f"), Some(Value::List(_))));
+        assert_eq!(value.get("g"), Some(&Value::Float(-1.5)));
+        assert_eq!(value.get("discarded"), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse("{:a [1 2}"),
+            Err("Invalid EDN on line 1: unmatched closing delimiter".to_string())
+        );
+        assert_eq!(
+            parse("{:a\n 1"),
+            Err("Invalid EDN on line 2: expected '}'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_sample_config() {
+        let content = std::fs::read_to_string("graph/logseq/config.edn").unwrap();
+        let config = parse(&content).unwrap();
+        assert_eq!(
+            config.get("file/name-format"),
+            Some(&Value::Keyword("triple-lowbar".to_string()))
+        );
+    }
+}
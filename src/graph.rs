@@ -0,0 +1,181 @@
+//! An in-memory view of a whole logseq graph, used by the linter.
+
+pub mod links;
+pub mod namespaces;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use links::LinkGraph;
+use namespaces::NamespaceTree;
+use regex::Regex;
+
+use crate::config::GraphConfig;
+use crate::indexer::MdWalker;
+use crate::parsing::block::{Block, BlockBuilder};
+use crate::parsing::file::{File, FileBuilder};
+use crate::parsing::markup;
+
+/// A parsed markdown file together with its blocks and raw content.
+pub struct Page {
+    /// The parsed file
+    pub file: File,
+    /// Every block in the file, at any depth
+    pub blocks: Vec<Block>,
+    /// The raw markdown content of the file
+    pub content: String,
+    /// The content with code, HTML and URLs blanked out, see `markup::prose`
+    pub prose: String,
+}
+
+/// Every page in a logseq graph.
+pub struct Graph {
+    /// The pages of the graph, in the order they were walked
+    pub pages: Vec<Page>,
+    /// Normalized page titles to their index in `pages`
+    titles: HashMap<String, usize>,
+    /// The graph's `logseq/config.edn`
+    pub config: GraphConfig,
+    /// The links between the pages and blocks
+    pub links: LinkGraph,
+    /// The namespaces of `a/b/c` style page titles
+    pub namespaces: NamespaceTree,
+}
+
+impl Graph {
+    /// Walk `root` and parse every markdown file into a page.
+    pub fn load(root: &str) -> Result<Graph, String> {
+        let config = GraphConfig::load(Path::new(root))?;
+        let mut pages = vec![];
+        for file in MdWalker::new(root).with_config(&config) {
+            let (path, ast, content) = file?;
+            let file = FileBuilder::new()
+                .with_path(path.clone())
+                .with_root(PathBuf::from(root))
+                .with_config(&config)
+                .build(&content, &ast)?;
+            let blocks = BlockBuilder::new()
+                .with_file_id(file.id.clone())
+                .with_file_path(path)
+                .with_normalizer(config.normalizer())
+                .with_separated_by_commas(config.property_separated_by_commas.clone())
+                .build_all(&content, &ast)?;
+            let prose = markup::prose(
+                &content,
+                0,
+                content.len(),
+                ast.children().map_or(&[], |c| c),
+            );
+            pages.push(Page {
+                file,
+                blocks,
+                content,
+                prose,
+            });
+        }
+        let titles = pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| (page.file.normalized_title.clone(), i))
+            .collect();
+        let links = LinkGraph::from_pages(&pages, config.normalizer());
+        let namespaces = NamespaceTree::from_pages(&pages, config.normalizer());
+        Ok(Graph {
+            pages,
+            titles,
+            config,
+            links,
+            namespaces,
+        })
+    }
+
+    /// Find the page a link or tag refers to, by its normalized name.
+    pub fn resolve(&self, name: &str) -> Option<&Page> {
+        self.titles
+            .get(&self.links.key(name))
+            .map(|i| &self.pages[*i])
+    }
+
+    /// Find a block by id, with the page it is on.
+    pub fn block(&self, id: &str) -> Option<(&Page, &Block)> {
+        self.pages.iter().find_map(|page| {
+            page.blocks
+                .iter()
+                .find(|block| block.id == id)
+                .map(|block| (page, block))
+        })
+    }
+
+    /// A pattern for the normalized page name `key` as a whole word in normalized text. Not
+    /// `\b`, which needs a word character at each end of the name and so misses `c++`.
+    fn mention(key: &str) -> Option<Regex> {
+        Regex::new(&format!(r"(?:^|\W){}(?:\W|$)", regex::escape(key))).ok()
+    }
+
+    /// Blocks on other pages that mention a page by name without linking to it. Names are
+    /// compared the way links are, so `Café` mentions `cafe` if accents are removed.
+    pub fn unlinked_references(&self, name: &str) -> Vec<(&Page, &Block)> {
+        let key = self.links.key(name);
+        let Some(mention) = Self::mention(&key) else {
+            return vec![];
+        };
+        let normalizer = self.config.normalizer();
+        let target = self.links.page(name);
+        let mut references = vec![];
+        for page in self.pages.iter() {
+            if page.file.normalized_title == key {
+                continue;
+            }
+            for block in page.blocks.iter() {
+                let node = links::Node::Block(block.id.clone());
+                let linked = self.links.outgoing(&node).any(|edge| edge.target == target);
+                if !linked && mention.is_match(&normalizer.normalize(&block.content)) {
+                    references.push((page, block));
+                }
+            }
+        }
+        references
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let page = graph
+            .pages
+            .iter()
+            .find(|p| p.file.title == "tests/parsing/blocks/hierarchy")
+            .unwrap();
+        assert_eq!(page.blocks.len(), 5);
+        assert!(page.blocks.iter().all(|b| b.file_id == page.file.id));
+    }
+
+    #[test]
+    fn test_load_errors_name_the_file() {
+        let error = Graph::load("tests/graphs/broken").err().unwrap();
+        assert!(error.starts_with("tests/graphs/broken/pages/not_utf8.md: "));
+    }
+
+    #[test]
+    fn test_mention() {
+        let mention = Graph::mention("c++").unwrap();
+        assert!(mention.is_match("c++"));
+        assert!(mention.is_match("learning c++, slowly"));
+        assert!(!mention.is_match("c++17"));
+        assert!(!Graph::mention("beta").unwrap().is_match("alphabeta"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let page = graph.resolve("Tests/Parsing/Blocks/Hierarchy").unwrap();
+        assert_eq!(page.file.title, "tests/parsing/blocks/hierarchy");
+        assert!(graph.resolve("tests/parsing/blocks").is_none());
+        let page = graph.resolve("  TESTS/Parsing/Blocks/Hierarchy ").unwrap();
+        assert_eq!(page.file.title, "tests/parsing/blocks/hierarchy");
+    }
+}
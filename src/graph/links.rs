@@ -0,0 +1,400 @@
+//! Links between pages and blocks, followed in either direction.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use regex::Regex;
+
+use crate::graph::Page;
+use crate::parsing::name::NameNormalizer;
+
+/// A page or a block of the link graph.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Node {
+    /// A page by its key, see `LinkGraph::key`. The page does not have to exist.
+    Page(String),
+    /// A block by its id
+    Block(String),
+}
+
+/// How one node refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// `[[page]]`
+    Link,
+    /// `#page`, `#[[page]]` or `tags:: page`
+    Tag,
+    /// `((uuid))`
+    BlockRef,
+    /// `{{embed [[page]]}}` or `{{embed ((uuid))}}`
+    Embed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub source: Node,
+    pub target: Node,
+    pub kind: EdgeKind,
+}
+
+/// Every link, tag, block reference and embed of a graph, with the pages and blocks they
+/// connect. Links are found from the source, this also finds them from the target.
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    edges: Vec<Edge>,
+    /// The indices of the edges from each node
+    outgoing: HashMap<Node, Vec<usize>>,
+    /// The indices of the edges to each node
+    incoming: HashMap<Node, Vec<usize>>,
+    /// The key of the page each block is on
+    block_pages: HashMap<String, String>,
+    /// Page names by key: the title of pages that exist, and how the others were first linked
+    names: BTreeMap<String, String>,
+    /// The keys of the pages that exist
+    pages: HashSet<String>,
+    /// The keys of aliases to the key of the page that owns them
+    aliases: HashMap<String, String>,
+    /// How page names become keys
+    normalizer: NameNormalizer,
+}
+
+impl LinkGraph {
+    pub fn new() -> LinkGraph {
+        LinkGraph::default()
+    }
+
+    pub fn with_normalizer(mut self, normalizer: NameNormalizer) -> LinkGraph {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Logseq page names are case insensitive, so pages are keyed by their normalized name.
+    /// An alias is keyed as the page that owns it.
+    pub fn key(&self, name: &str) -> String {
+        let key = self.normalizer.normalize(name);
+        self.aliases.get(&key).cloned().unwrap_or(key)
+    }
+
+    pub fn page(&self, name: &str) -> Node {
+        Node::Page(self.key(name))
+    }
+
+    /// Add a page, named after its title.
+    pub fn add_page(&mut self, title: &str) {
+        self.names.insert(self.key(title), title.to_string());
+        self.pages.insert(self.key(title));
+    }
+
+    /// Make links to `alias` go to the page titled `title`. A page titled `alias`, or a page
+    /// that claimed the alias first, keeps it, and `false` is returned.
+    pub fn add_alias(&mut self, alias: &str, title: &str) -> bool {
+        let alias = self.normalizer.normalize(alias);
+        if self.pages.contains(&alias) || self.aliases.contains_key(&alias) {
+            return false;
+        }
+        let owner = self.key(title);
+        self.aliases.insert(alias, owner);
+        true
+    }
+
+    /// Add a block on the page titled `page`.
+    pub fn add_block(&mut self, id: &str, page: &str) {
+        self.block_pages.insert(id.to_string(), self.key(page));
+    }
+
+    /// Add an edge. Pages that were not added are named the way the edge links to them.
+    pub fn add_edge(&mut self, source: Node, target: Node, kind: EdgeKind) {
+        let index = self.edges.len();
+        self.outgoing.entry(source.clone()).or_default().push(index);
+        self.incoming.entry(target.clone()).or_default().push(index);
+        self.edges.push(Edge {
+            source,
+            target,
+            kind,
+        });
+    }
+
+    /// Add a page that does not exist, named the way it was linked, and return its node.
+    fn linked_page(&mut self, name: &str) -> Node {
+        let node = self.page(name);
+        if let Node::Page(key) = &node {
+            self.names
+                .entry(key.clone())
+                .or_insert_with(|| name.trim().to_string());
+        }
+        node
+    }
+
+    /// Build the link graph of some pages. Links in a block come from the block, anything
+    /// outside of blocks, such as page properties, comes from the page.
+    pub fn from_pages(pages: &[Page], normalizer: NameNormalizer) -> LinkGraph {
+        let mut graph = LinkGraph::new().with_normalizer(normalizer);
+        for page in pages {
+            graph.add_page(&page.file.title);
+        }
+        // Pages claim aliases in path order, whatever order they were walked in
+        let mut by_path: Vec<&Page> = pages.iter().collect();
+        by_path.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+        for page in by_path {
+            for alias in page.file.aliases.iter() {
+                graph.add_alias(alias, &page.file.title);
+            }
+        }
+        for page in pages {
+            let title = &page.file.title;
+            let mut page_links = page.file.wikilinks.clone();
+            let mut page_tags = page.file.tags.clone();
+            let mut page_refs = page.file.block_refs.clone();
+            for block in page.blocks.iter() {
+                graph.add_block(&block.id, title);
+                let source = Node::Block(block.id.clone());
+                let (embedded_pages, embedded_blocks) = Self::embeds(&block.content);
+                // Embeds in code were not picked up as links or references, leave them out
+                let embedded_pages =
+                    without(&embedded_pages, &without(&embedded_pages, &block.wikilinks));
+                let embedded_blocks = without(
+                    &embedded_blocks,
+                    &without(&embedded_blocks, &block.block_refs),
+                );
+                for name in without(&block.wikilinks, &embedded_pages) {
+                    let target = graph.linked_page(&name);
+                    graph.add_edge(source.clone(), target, EdgeKind::Link);
+                }
+                for name in block.tags.iter() {
+                    let target = graph.linked_page(name);
+                    graph.add_edge(source.clone(), target, EdgeKind::Tag);
+                }
+                for id in without(&block.block_refs, &embedded_blocks) {
+                    graph.add_edge(source.clone(), Node::Block(id), EdgeKind::BlockRef);
+                }
+                for name in embedded_pages.iter() {
+                    let target = graph.linked_page(name);
+                    graph.add_edge(source.clone(), target, EdgeKind::Embed);
+                }
+                for id in embedded_blocks {
+                    graph.add_edge(source.clone(), Node::Block(id), EdgeKind::Embed);
+                }
+                page_links = without(&page_links, &block.wikilinks);
+                page_tags = without(&page_tags, &block.tags);
+                page_refs = without(&page_refs, &block.block_refs);
+            }
+            let source = graph.page(title);
+            for name in page_links {
+                let target = graph.linked_page(&name);
+                graph.add_edge(source.clone(), target, EdgeKind::Link);
+            }
+            for name in page_tags {
+                let target = graph.linked_page(&name);
+                graph.add_edge(source.clone(), target, EdgeKind::Tag);
+            }
+            for id in page_refs {
+                graph.add_edge(source.clone(), Node::Block(id), EdgeKind::BlockRef);
+            }
+        }
+        graph
+    }
+
+    /// The pages and block ids embedded in some content.
+    fn embeds(content: &str) -> (Vec<String>, Vec<String>) {
+        let re =
+            Regex::new(r"\{\{embed\s+(?:\[\[([^\]]+)\]\]|\(\(([0-9a-f-]{36})\)\))\s*\}\}").unwrap();
+        let mut pages = vec![];
+        let mut blocks = vec![];
+        for captures in re.captures_iter(content) {
+            if let Some(page) = captures.get(1) {
+                pages.push(page.as_str().trim().to_string());
+            } else if let Some(id) = captures.get(2) {
+                blocks.push(id.as_str().to_string());
+            }
+        }
+        (pages, blocks)
+    }
+
+    pub fn outgoing(&self, node: &Node) -> impl Iterator<Item = &Edge> {
+        let indices = self.outgoing.get(node).map(Vec::as_slice).unwrap_or(&[]);
+        indices.iter().map(|i| &self.edges[*i])
+    }
+
+    pub fn incoming(&self, node: &Node) -> impl Iterator<Item = &Edge> {
+        let indices = self.incoming.get(node).map(Vec::as_slice).unwrap_or(&[]);
+        indices.iter().map(|i| &self.edges[*i])
+    }
+
+    /// The key of the page a node is on, which is the page itself for pages.
+    /// Blocks that are not in the graph are on no page.
+    pub fn page_of<'a>(&'a self, node: &'a Node) -> Option<&'a str> {
+        match node {
+            Node::Page(key) => Some(key),
+            Node::Block(id) => self.block_pages.get(id).map(String::as_str),
+        }
+    }
+
+    /// How a page is named, its title if it exists.
+    pub fn name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.names.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// Whether a page with this name was added, rather than only linked to.
+    pub fn is_page(&self, name: &str) -> bool {
+        self.pages.contains(&self.key(name))
+    }
+
+    /// Links, tags and embeds of a page from other pages and their blocks.
+    pub fn backlinks(&self, name: &str) -> Vec<&Edge> {
+        let page = self.page(name);
+        let key = self.key(name);
+        self.incoming(&page)
+            .filter(|edge| self.page_of(&edge.source) != Some(key.as_str()))
+            .collect()
+    }
+
+    /// The names of the pages that link to a page, in order.
+    pub fn backlink_pages(&self, name: &str) -> Vec<String> {
+        let keys: BTreeSet<&str> = self
+            .backlinks(name)
+            .into_iter()
+            .filter_map(|edge| self.page_of(&edge.source))
+            .collect();
+        keys.into_iter()
+            .map(|key| self.name(key).to_string())
+            .collect()
+    }
+
+    /// Groups of pages that are connected by links in either direction, largest first.
+    /// Block references and embeds connect the pages the blocks are on.
+    pub fn components(&self) -> Vec<Vec<String>> {
+        let keys: Vec<&str> = self.names.keys().map(String::as_str).collect();
+        let index: HashMap<&str, usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        let mut parents: Vec<usize> = (0..keys.len()).collect();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for edge in self.edges.iter() {
+            let source = self.page_of(&edge.source).and_then(|key| index.get(key));
+            let target = self.page_of(&edge.target).and_then(|key| index.get(key));
+            if let (Some(source), Some(target)) = (source, target) {
+                let (source, target) = (root(&mut parents, *source), root(&mut parents, *target));
+                parents[source] = target;
+            }
+        }
+        let mut components: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            let root = root(&mut parents, i);
+            components
+                .entry(root)
+                .or_default()
+                .push(self.name(key).to_string());
+        }
+        let mut components: Vec<Vec<String>> = components.into_values().collect();
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+        components
+    }
+}
+
+/// `items` without one occurrence of each of `removed`.
+fn without(items: &[String], removed: &[String]) -> Vec<String> {
+    let mut items = items.to_vec();
+    for item in removed {
+        if let Some(i) = items.iter().position(|other| other == item) {
+            items.remove(i);
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+
+    #[test]
+    fn test_backlinks() {
+        let graph = Graph::load("tests/graphs/links").unwrap();
+        let links = &graph.links;
+        // Pages are walked in no particular order
+        let kinds: HashSet<EdgeKind> = links.backlinks("Beta").iter().map(|e| e.kind).collect();
+        assert_eq!(links.backlinks("Beta").len(), 3);
+        assert_eq!(
+            kinds,
+            HashSet::from([EdgeKind::Link, EdgeKind::Embed, EdgeKind::Tag])
+        );
+        assert_eq!(links.backlink_pages("beta"), vec!["alpha", "gamma"]);
+        assert_eq!(links.backlink_pages("alpha"), vec!["beta"]);
+        assert!(links.backlinks("lonely").is_empty());
+    }
+
+    #[test]
+    fn test_block_refs() {
+        let graph = Graph::load("tests/graphs/links").unwrap();
+        let block = Node::Block("6630a6a4-0000-4000-8000-000000000001".to_string());
+        let refs: Vec<&Edge> = graph.links.incoming(&block).collect();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].kind, EdgeKind::BlockRef);
+        assert_eq!(graph.links.page_of(&refs[0].source), Some("gamma"));
+        assert_eq!(graph.links.page_of(&block), Some("beta"));
+    }
+
+    #[test]
+    fn test_components() {
+        let graph = Graph::load("tests/graphs/links").unwrap();
+        let components = graph.links.components();
+        assert_eq!(
+            components[0],
+            vec!["alpha", "beta", "gamma", "missing page"]
+        );
+        // The journal, contents, lonely and paragraph pages are on their own
+        assert_eq!(components.len(), 5);
+        assert!(components[1..].iter().all(|component| component.len() == 1));
+        assert!(graph.links.is_page("Gamma"));
+        assert!(!graph.links.is_page("missing page"));
+    }
+
+    #[test]
+    fn test_normalized_names() {
+        let mut links = LinkGraph::new().with_normalizer(NameNormalizer::new(true));
+        links.add_page("Café Crème");
+        links.add_page("Menu");
+        let (source, target) = (links.page("menu"), links.page(" CAFE  creme"));
+        links.add_edge(source, target, EdgeKind::Tag);
+        assert!(links.is_page("cafe creme"));
+        assert_eq!(links.backlink_pages("café crème"), vec!["Menu"]);
+        assert_eq!(links.components().len(), 1);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let graph = Graph::load("tests/graphs/aliases").unwrap();
+        let links = &graph.links;
+        assert_eq!(links.key("ECMAScript"), "javascript");
+        assert_eq!(links.key("ts"), "typescript");
+        assert_eq!(links.key("JS"), "javascript");
+        // Titles win over aliases
+        assert_eq!(links.key("JavaScript"), "javascript");
+        assert_eq!(links.key("first"), "first");
+        assert_eq!(
+            links.backlink_pages("javascript"),
+            vec!["reader", "typescript"]
+        );
+        assert_eq!(links.backlink_pages("typescript"), vec!["reader"]);
+    }
+
+    #[test]
+    fn test_unlinked_references() {
+        let graph = Graph::load("tests/graphs/links").unwrap();
+        let references: Vec<&str> = graph
+            .unlinked_references("beta")
+            .iter()
+            .map(|(_, block)| block.content.as_str())
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                "- Mentions Beta without linking it",
+                "- Mentions Bêta with an accent"
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! The tree of namespaces that `a/b/c` style page titles make up.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::graph::Page;
+use crate::parsing::name::NameNormalizer;
+
+/// A namespace, or a page in one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespace {
+    /// The full name, e.g. `a/b`, as its page is titled or as the first page under it wrote it
+    pub name: String,
+    /// The title of the page with this name, if there is one
+    pub title: Option<String>,
+    /// The keys of the namespaces and pages directly under this one
+    children: BTreeSet<String>,
+}
+
+/// Every namespaced page and the namespaces above it, keyed by normalized name.
+#[derive(Debug, Default)]
+pub struct NamespaceTree {
+    namespaces: BTreeMap<String, Namespace>,
+    normalizer: NameNormalizer,
+}
+
+impl NamespaceTree {
+    pub fn from_pages(pages: &[Page], normalizer: NameNormalizer) -> NamespaceTree {
+        let mut tree = NamespaceTree {
+            namespaces: BTreeMap::new(),
+            normalizer,
+        };
+        for page in pages
+            .iter()
+            .filter(|page| page.file.namespace_parent.is_some())
+        {
+            let names = page.file.namespace_path.iter().chain([&page.file.title]);
+            let mut parent: Option<String> = None;
+            for name in names {
+                let key = normalizer.normalize(name);
+                tree.namespaces
+                    .entry(key.clone())
+                    .or_insert_with(|| Namespace {
+                        name: name.clone(),
+                        title: None,
+                        children: BTreeSet::new(),
+                    });
+                if let Some(parent) = parent {
+                    tree.namespaces
+                        .get_mut(&parent)
+                        .unwrap()
+                        .children
+                        .insert(key.clone());
+                }
+                parent = Some(key);
+            }
+        }
+        for page in pages {
+            let key = normalizer.normalize(&page.file.title);
+            if let Some(namespace) = tree.namespaces.get_mut(&key) {
+                namespace.name = page.file.title.clone();
+                namespace.title = Some(page.file.title.clone());
+            }
+        }
+        tree
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Namespace> {
+        self.namespaces.get(&self.normalizer.normalize(name))
+    }
+
+    /// The namespaces and pages directly under a namespace, by name.
+    pub fn children(&self, name: &str) -> Vec<&Namespace> {
+        self.get(name)
+            .map(|namespace| {
+                namespace
+                    .children
+                    .iter()
+                    .map(|key| &self.namespaces[key])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The outermost namespaces, by name.
+    pub fn roots(&self) -> Vec<&Namespace> {
+        self.namespaces
+            .iter()
+            .filter(|(key, _)| !key.contains('/'))
+            .map(|(_, namespace)| namespace)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Graph;
+
+    #[test]
+    fn test_namespaces() {
+        let graph = Graph::load("tests/graphs/namespaces").unwrap();
+        let tree = &graph.namespaces;
+        let names = |namespaces: Vec<&super::Namespace>| -> Vec<String> {
+            namespaces.iter().map(|n| n.name.clone()).collect()
+        };
+        assert_eq!(names(tree.roots()), vec!["areas", "Projects"]);
+        assert_eq!(names(tree.children("projects")), vec!["Projects/website"]);
+        assert_eq!(
+            names(tree.children("projects/website")),
+            vec!["projects/website/design"]
+        );
+        assert_eq!(tree.get("Areas/Health").unwrap().title, None);
+        assert_eq!(
+            tree.get("projects").unwrap().title.as_deref(),
+            Some("Projects")
+        );
+    }
+}
@@ -1,14 +1,22 @@
 //! Utilities for handling files and directories.
 
+use futures::{stream, Future, StreamExt, TryStreamExt};
 use glob::Pattern;
-use indicatif::ProgressIterator;
+use indicatif::ParallelProgressIterator;
 use markdown::mdast;
-use std::path::PathBuf;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::meilisearch::Meilisearch;
-use crate::parsing::block::BlockBuilder;
-use crate::parsing::file::FileBuilder;
+use crate::backend::SearchBackend;
+use crate::config::GraphConfig;
+use crate::graph::links::{EdgeKind, LinkGraph};
+use crate::manifest::{Entry, Manifest};
+use crate::parsing::block::{Block, BlockBuilder};
+use crate::parsing::file::{File, FileBuilder};
+use crate::parsing::name::NameNormalizer;
 
 /// Walks a directory tree and yields files matching a glob pattern.
 pub struct MdWalker {
@@ -16,6 +24,10 @@ pub struct MdWalker {
     walker: walkdir::IntoIter,
     /// The glob pattern to match.
     pattern: Pattern,
+    /// The root of the graph
+    root: PathBuf,
+    /// Paths relative to the root that are skipped, from the config's `:hidden`
+    hidden: Vec<PathBuf>,
 }
 
 impl MdWalker {
@@ -24,155 +36,445 @@ impl MdWalker {
         MdWalker {
             walker: WalkDir::new(path).into_iter(),
             pattern: Pattern::new("*.md").unwrap(),
+            root: PathBuf::from(path),
+            hidden: vec![],
         }
     }
-}
 
-impl Iterator for MdWalker {
-    type Item = Result<(PathBuf, mdast::Node, String), String>;
+    /// Skip the paths the graph's config hides.
+    pub fn with_config(mut self, config: &GraphConfig) -> MdWalker {
+        self.hidden = config
+            .hidden
+            .iter()
+            .map(|path| self.root.join(path.trim_start_matches('/')))
+            .collect();
+        self
+    }
 
-    /// Get the next file matching the pattern. Returns the markdown AST.
-    fn next(&mut self) -> Option<Self::Item> {
-        for entry in self.walker.by_ref() {
-            match entry {
+    /// Whether logseq ignores an entry: dot files, the `logseq` directory with its
+    /// backups and anything hidden in the config.
+    fn is_ignored(&self, entry: &walkdir::DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        let dotted = entry.file_name().to_string_lossy().starts_with('.');
+        let internal = entry.depth() == 1 && entry.file_name() == "logseq";
+        dotted || internal || self.hidden.iter().any(|h| entry.path() == h)
+    }
+}
+
+impl MdWalker {
+    /// Get the next file matching the pattern, without reading it. Errors come with the
+    /// path that could not be walked.
+    pub fn next_path(&mut self) -> Option<Result<PathBuf, (PathBuf, String)>> {
+        loop {
+            match self.walker.next()? {
+                Ok(e) if self.is_ignored(&e) => {
+                    if e.file_type().is_dir() {
+                        self.walker.skip_current_dir();
+                    }
+                }
                 Ok(e) if self.pattern.matches_path(e.path()) => {
-                    let content = match std::fs::read_to_string(e.path()) {
-                        Ok(content) => content,
-                        Err(msg) => return Some(Err(msg.to_string())),
+                    return Some(Ok(e.path().to_path_buf()))
+                }
+                Err(err) => {
+                    let path = err.path().unwrap_or(&self.root).to_path_buf();
+                    let reason = match err.io_error() {
+                        Some(io) => io.to_string(),
+                        None => err.to_string(),
                     };
-                    let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default());
-                    match ast {
-                        Ok(ast) => return Some(Ok((e.path().to_path_buf(), ast, content))),
-                        Err(msg) => return Some(Err(msg.to_string())),
-                    }
+                    return Some(Err((path, reason)));
                 }
-                Err(msg) => return Some(Err(msg.to_string())),
                 Ok(_) => continue,
             }
         }
-        None
+    }
+
+    /// Read a file and parse it into a markdown AST.
+    pub fn read(path: PathBuf) -> Result<(PathBuf, mdast::Node, String), String> {
+        let content = std::fs::read_to_string(&path).map_err(|msg| msg.to_string())?;
+        let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default())
+            .map_err(|msg| msg.to_string())?;
+        Ok((path, ast, content))
+    }
+}
+
+impl Iterator for MdWalker {
+    type Item = Result<(PathBuf, mdast::Node, String), String>;
+
+    /// Get the next file matching the pattern. Returns the markdown AST.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_path().map(|path| {
+            let (path, reason) = match path {
+                Ok(path) => match Self::read(path.clone()) {
+                    Ok(file) => return Ok(file),
+                    Err(reason) => (path, reason),
+                },
+                Err(err) => err,
+            };
+            Err(format!("{}: {}", path.to_string_lossy(), reason))
+        })
+    }
+}
+
+/// What indexing a graph did.
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexReport {
+    /// Files that were parsed and indexed
+    pub indexed: usize,
+    /// Files that were skipped because they did not change since the last run
+    pub unchanged: usize,
+    /// Files that could not be indexed, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl fmt::Display for IndexReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Indexed {} files, {} unchanged",
+            self.indexed, self.unchanged
+        )?;
+        if !self.failed.is_empty() {
+            writeln!(f, "{} files could not be indexed:", self.failed.len())?;
+        }
+        for (path, reason) in self.failed.iter() {
+            writeln!(f, "  {}: {}", path, reason)?;
+        }
+        Ok(())
     }
 }
 
-pub struct Indexer {
-    pub db: Meilisearch,
+/// A file that was read, or found unchanged, for indexing.
+enum Parsed {
+    /// The file did not change since it was indexed, its manifest entry is kept
+    Unchanged(Entry),
+    /// The file changed and its documents have to be replaced
+    Changed {
+        entry: Entry,
+        file: Box<File>,
+        /// The file's blocks, if they are indexed
+        blocks: Option<Vec<Block>>,
+    },
 }
 
-impl Indexer {
-    pub async fn new() -> Indexer {
+pub struct Indexer<B: SearchBackend> {
+    pub db: B,
+    /// Where the manifest of indexed files is kept. Without one, every file is indexed.
+    manifest_path: Option<PathBuf>,
+    /// Whether to stop at the first file that can't be indexed
+    strict: bool,
+    /// The most documents sent to the backend at once
+    batch_size: usize,
+    /// The most batches sent to the backend at the same time
+    concurrency: usize,
+}
+
+impl<B: SearchBackend> Indexer<B> {
+    pub fn new(db: B) -> Indexer<B> {
         Indexer {
-            db: Meilisearch::new().await,
+            db,
+            manifest_path: None,
+            strict: false,
+            batch_size: 1000,
+            concurrency: 4,
         }
     }
 
-    pub async fn index_files(&self, path: &str, index_blocks: bool) -> Result<(), String> {
-        // An index is where the documents are stored.
-        let files = self.db.client.index("files");
-        let walker = MdWalker::new(path);
-        let mut tasks = vec![];
-        for file in walker
-            .into_iter()
-            .collect::<Vec<Result<(PathBuf, mdast::Node, String), String>>>()
-            .into_iter()
+    /// Only index files that changed since the manifest at `path` was saved.
+    pub fn with_manifest(mut self, path: PathBuf) -> Indexer<B> {
+        self.manifest_path = Some(path);
+        self
+    }
+
+    /// Fail on the first file that can't be read or parsed instead of reporting it
+    /// and indexing the rest.
+    pub fn with_strict(mut self, strict: bool) -> Indexer<B> {
+        self.strict = strict;
+        self
+    }
+
+    /// Send documents to the backend in batches of at most `batch_size`.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Indexer<B> {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Send at most `concurrency` batches to the backend at the same time.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Indexer<B> {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Index every markdown file under `root`. Ids are derived from paths, so
+    /// indexing the same graph again replaces documents rather than duplicating them.
+    ///
+    /// With a manifest, files whose content has not changed are skipped, and the
+    /// documents of files that were deleted or renamed are removed. Everything is
+    /// indexed again when the index schema changed.
+    ///
+    /// Files that can't be read or parsed are listed in the report, unless the indexer
+    /// is strict. Errors from the backend always stop indexing.
+    pub async fn index_files(&self, root: &str, index_blocks: bool) -> Result<IndexReport, String> {
+        let migrated = self.db.migrate().await?;
+        let previous = match &self.manifest_path {
+            Some(_) if migrated => {
+                eprintln!("The index schema changed, indexing every file again");
+                Manifest::default()
+            }
+            Some(path) => Manifest::load(path)?,
+            None => Manifest::default(),
+        };
+        let mut current = Manifest::default();
+        let mut report = IndexReport::default();
+        let config = GraphConfig::load(Path::new(root))?;
+        let mut walker = MdWalker::new(root).with_config(&config);
+        let mut paths = vec![];
+        while let Some(path) = walker.next_path() {
+            match path {
+                Ok(path) => paths.push(path),
+                Err((path, reason)) if self.strict => {
+                    return Err(format!("{}: {}", path.to_string_lossy(), reason))
+                }
+                Err((path, reason)) => report
+                    .failed
+                    .push((path.to_string_lossy().to_string(), reason)),
+            }
+        }
+        // Parsing is the slow part, so files are parsed on every core
+        let parsed: Vec<Result<Parsed, String>> = paths
+            .par_iter()
             .progress()
-        {
-            let doc = match file {
-                Ok((path, ast, content)) => {
-                    let file = FileBuilder::new()
-                        .with_path(path.clone())
-                        .build(&content, &ast)?;
-                    if index_blocks {
-                        self.index_blocks(&ast, &content, file.id.clone(), path)
-                            .await
-                            .map_err(|e| e.to_string())?;
+            .map(|path| {
+                let relative = FileBuilder::get_relative_path(path, Some(Path::new(root)));
+                Self::parse(path, root, &config, &previous, &relative, index_blocks)
+            })
+            .collect();
+        let mut files = vec![];
+        let mut blocks = vec![];
+        for (path, parsed) in paths.iter().zip(parsed) {
+            let relative = FileBuilder::get_relative_path(path, Some(Path::new(root)));
+            match parsed {
+                Ok(Parsed::Unchanged(entry)) => {
+                    current.files.insert(relative, entry);
+                    report.unchanged += 1;
+                }
+                Ok(Parsed::Changed {
+                    entry,
+                    file,
+                    blocks: file_blocks,
+                }) => {
+                    blocks.extend(file_blocks.unwrap_or_default());
+                    current.files.insert(relative, entry);
+                    files.push(*file);
+                    report.indexed += 1;
+                }
+                Err(reason) if self.strict => {
+                    return Err(format!("{}: {}", path.to_string_lossy(), reason))
+                }
+                Err(reason) => {
+                    // Keep the documents from the last time the file could be indexed
+                    if let Some(entry) = previous.files.get(&relative) {
+                        current.files.insert(relative, entry.clone());
                     }
-                    file
+                    report
+                        .failed
+                        .push((path.to_string_lossy().to_string(), reason));
                 }
-                Err(msg) => return Err(msg.to_string()),
-            };
-            let task = files
-                .add_documents(&[doc], Some("id"))
-                .await
-                .map_err(|e| e.to_string())?;
-            tasks.push(task);
+            }
         }
-        for task in tasks {
-            task.wait_for_completion(&self.db.client, None, None)
-                .await
-                .map_err(|e| e.to_string())?;
+        // Before linking, which adds unchanged files whose backlinks changed but not their blocks
+        let changed: Vec<String> = files.iter().map(|file| file.id.clone()).collect();
+        self.link(&mut files, &mut current, config.normalizer())
+            .await?;
+        if index_blocks {
+            // Blocks that were removed from a changed file would be left behind otherwise
+            self.batched(&changed, |ids| self.db.delete_blocks(ids))
+                .await?;
+            self.batched(&blocks, |batch| self.db.upsert_blocks(batch))
+                .await?;
         }
-        Ok(())
+        self.batched(&files, |batch| self.db.upsert_files(batch))
+            .await?;
+        let removed: Vec<String> = previous
+            .removed(&current)
+            .map(|(_, entry)| entry.file_id.clone())
+            .collect();
+        self.batched(&removed, |ids| self.db.delete_files(ids))
+            .await?;
+        self.batched(&removed, |ids| self.db.delete_blocks(ids))
+            .await?;
+        self.db.flush().await?;
+        if let Some(path) = &self.manifest_path {
+            current.save(path)?;
+        }
+        Ok(report)
+    }
+
+    /// Read and parse the file at `path`, unless the manifest shows it did not change.
+    fn parse(
+        path: &Path,
+        root: &str,
+        config: &GraphConfig,
+        previous: &Manifest,
+        relative: &str,
+        index_blocks: bool,
+    ) -> Result<Parsed, String> {
+        let mtime = Manifest::mtime(path)?;
+        if previous.is_untouched(relative, mtime, index_blocks) {
+            return Ok(Parsed::Unchanged(previous.files[relative].clone()));
+        }
+        let (path, ast, content) = MdWalker::read(path.to_path_buf())?;
+        let hash = Manifest::hash(&content);
+        if previous.is_unchanged(relative, &hash, index_blocks) {
+            return Ok(Parsed::Unchanged(Entry {
+                mtime,
+                ..previous.files[relative].clone()
+            }));
+        }
+        let file = FileBuilder::new()
+            .with_path(path.clone())
+            .with_root(PathBuf::from(root))
+            .with_config(config)
+            .build(&content, &ast)?;
+        let blocks = if index_blocks {
+            let blocks = BlockBuilder::new()
+                .with_file_id(file.id.clone())
+                .with_file_path(path)
+                .with_normalizer(config.normalizer())
+                .with_separated_by_commas(config.property_separated_by_commas.clone())
+                .build_all(&content, &ast)?;
+            Some(blocks)
+        } else {
+            None
+        };
+        let entry = Entry {
+            mtime,
+            hash,
+            file_id: file.id.clone(),
+            blocks: index_blocks,
+            title: file.title.clone(),
+            aliases: file.aliases.clone(),
+            links: file.wikilinks.clone(),
+            tags: file.tags.clone(),
+            ..Entry::default()
+        };
+        Ok(Parsed::Changed {
+            entry,
+            file: Box::new(file),
+            blocks,
+        })
     }
 
-    async fn index_blocks(
+    /// Set the backlinks of the changed files. Unchanged files whose backlinks changed
+    /// because of them are fetched from the backend and added to `files` to be updated.
+    /// Only the links in the manifest are needed, so unchanged files are not read again.
+    async fn link(
         &self,
-        ast: &mdast::Node,
-        content: &str,
-        file_id: String,
-        file_path: PathBuf,
+        files: &mut Vec<File>,
+        current: &mut Manifest,
+        normalizer: NameNormalizer,
     ) -> Result<(), String> {
-        let blocks_index = self.db.client.index("blocks");
-        let mut tasks = vec![];
-
-        for child in ast.children().unwrap_or(&vec![]).iter() {
-            if let mdast::Node::List(list) = child {
-                for item in list.children.iter() {
-                    if let mdast::Node::ListItem(list_item) = item {
-                        let new_blocks = BlockBuilder::new()
-                            .with_file_id(file_id.clone())
-                            .with_file_path(file_path.clone())
-                            .build(content, list_item)?;
-                        let task_info = blocks_index
-                            .add_documents(&new_blocks, Some("id"))
-                            .await
-                            .map_err(|e| e.to_string())?;
-                        tasks.push(task_info);
-                    }
-                }
-            } else if let mdast::Node::ListItem(list_item) = child {
-                let new_blocks = BlockBuilder::new()
-                    .with_file_id(file_id.clone())
-                    .with_file_path(file_path.clone())
-                    .build(content, list_item)?;
-                let task_info = blocks_index
-                    .add_documents(&new_blocks, Some("id"))
-                    .await
-                    .map_err(|e| e.to_string())?;
-                tasks.push(task_info);
+        let mut links = LinkGraph::new().with_normalizer(normalizer);
+        for entry in current.files.values() {
+            links.add_page(&entry.title);
+        }
+        for entry in current.files.values() {
+            for alias in entry.aliases.iter() {
+                links.add_alias(alias, &entry.title);
             }
         }
-        for task in tasks {
-            task.wait_for_completion(&self.db.client, None, None)
-                .await
-                .map_err(|e| e.to_string())?;
+        for entry in current.files.values() {
+            let source = links.page(&entry.title);
+            for name in entry.links.iter() {
+                let target = links.page(name);
+                links.add_edge(source.clone(), target, EdgeKind::Link);
+            }
+            for name in entry.tags.iter() {
+                let target = links.page(name);
+                links.add_edge(source.clone(), target, EdgeKind::Tag);
+            }
+        }
+        let changed: HashMap<String, usize> = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.id.clone(), i))
+            .collect();
+        for entry in current.files.values_mut() {
+            let backlinks = links.backlink_pages(&entry.title);
+            let backlink_count = links.backlinks(&entry.title).len();
+            let file = match changed.get(&entry.file_id) {
+                Some(i) => &mut files[*i],
+                None if (&backlinks, backlink_count)
+                    == (&entry.backlinks, entry.backlink_count) =>
+                {
+                    continue
+                }
+                None => match self.db.get_file(&entry.file_id).await? {
+                    Some(file) => {
+                        files.push(file);
+                        files.last_mut().unwrap()
+                    }
+                    None => continue,
+                },
+            };
+            file.backlinks = backlinks.clone();
+            file.backlink_count = backlink_count;
+            entry.backlinks = backlinks;
+            entry.backlink_count = backlink_count;
         }
         Ok(())
     }
+
+    /// Send `documents` to the backend in batches, a few batches at a time.
+    async fn batched<'a, T, F, Fut>(&self, documents: &'a [T], send: F) -> Result<(), String>
+    where
+        F: Fn(&'a [T]) -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        stream::iter(documents.chunks(self.batch_size).map(send))
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parsing::file::File;
+    use crate::backend::memory::MemoryBackend;
+    use crate::parsing::property::PropertyValue;
 
     use super::*;
 
     use std::collections::HashMap;
 
+    #[test]
+    fn test_walker_skips_hidden() {
+        let paths: Vec<PathBuf> = MdWalker::new("graph")
+            .with_config(&GraphConfig {
+                hidden: vec!["/journals".to_string()],
+                ..GraphConfig::default()
+            })
+            .map(|file| file.unwrap().0)
+            .collect();
+        assert!(!paths.is_empty());
+        assert!(paths.iter().all(|path| path.starts_with("graph/pages")));
+    }
+
     #[tokio::test]
     async fn test_index_blocks() {
         let path = PathBuf::from("graph/pages/tests___parsing___blocks___hierarchy.md");
         let content = std::fs::read_to_string(&path).unwrap();
         let file_id = "test".to_string();
         let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
-        let db = Meilisearch::new().await;
-        let blocks_index = db.client.index("blocks");
-        blocks_index.delete_all_documents().await.unwrap();
-        Indexer::new()
-            .await
-            .index_blocks(&ast, &content, file_id.clone(), path)
-            .await
+        let blocks = BlockBuilder::new()
+            .with_file_id(file_id.clone())
+            .with_file_path(path)
+            .build_all(&content, &ast)
             .unwrap();
-        let mut blocks = blocks_index.get_documents::<Block>().await.unwrap().results;
+        let indexer = Indexer::new(MemoryBackend::new());
+        indexer.db.upsert_blocks(&blocks).await.unwrap();
+        let mut blocks = indexer.db.search_blocks("", None, 20).await.unwrap();
         assert_eq!(blocks.len(), 5);
         blocks.sort_by_key(|b| b.content.clone());
         println!(
@@ -195,12 +497,21 @@ mod tests {
             block1,
             &Block {
                 id: block1.id.clone(),
+                id_span: None,
+                span: block1.span,
                 file_id: file_id.clone(),
                 parent_block_id: None,
-                content: content,
+                content,
                 properties: HashMap::new(),
                 wikilinks: vec![],
                 tags: vec![],
+                normalized_wikilinks: vec![],
+                normalized_tags: vec![],
+                block_refs: vec![],
+                marker: None,
+                priority: None,
+                scheduled: None,
+                deadline: None,
             }
         );
         let content = "- Ipsum".to_string();
@@ -215,12 +526,21 @@ mod tests {
             block2,
             &Block {
                 id: block2.id.clone(),
+                id_span: None,
+                span: block2.span,
                 file_id: file_id.clone(),
                 parent_block_id: Some(block1.id.clone()),
-                content: content,
+                content,
                 properties: HashMap::new(),
                 wikilinks: vec![],
                 tags: vec![],
+                normalized_wikilinks: vec![],
+                normalized_tags: vec![],
+                block_refs: vec![],
+                marker: None,
+                priority: None,
+                scheduled: None,
+                deadline: None,
             }
         );
         let content = "- Dolor".to_string();
@@ -235,12 +555,21 @@ mod tests {
             block3,
             &Block {
                 id: block3.id.clone(),
+                id_span: None,
+                span: block3.span,
                 file_id: file_id.clone(),
                 parent_block_id: Some(block1.id.clone()),
-                content: content,
+                content,
                 properties: HashMap::new(),
                 wikilinks: vec![],
                 tags: vec![],
+                normalized_wikilinks: vec![],
+                normalized_tags: vec![],
+                block_refs: vec![],
+                marker: None,
+                priority: None,
+                scheduled: None,
+                deadline: None,
             }
         );
         let content = "- Sit".to_string();
@@ -255,12 +584,21 @@ mod tests {
             block4,
             &Block {
                 id: block4.id.clone(),
+                id_span: None,
+                span: block4.span,
                 file_id: file_id.clone(),
                 parent_block_id: Some(block3.id.clone()),
                 content: "- Sit".to_string(),
                 properties: HashMap::new(),
                 wikilinks: vec![],
                 tags: vec![],
+                normalized_wikilinks: vec![],
+                normalized_tags: vec![],
+                block_refs: vec![],
+                marker: None,
+                priority: None,
+                scheduled: None,
+                deadline: None,
             }
         );
         let content = "- Amet".to_string();
@@ -275,12 +613,21 @@ mod tests {
             block5,
             &Block {
                 id: block5.id.clone(),
+                id_span: None,
+                span: block5.span,
                 file_id: file_id.clone(),
                 parent_block_id: None,
                 content: "- Amet".to_string(),
                 properties: HashMap::new(),
                 wikilinks: vec![],
                 tags: vec![],
+                normalized_wikilinks: vec![],
+                normalized_tags: vec![],
+                block_refs: vec![],
+                marker: None,
+                priority: None,
+                scheduled: None,
+                deadline: None,
             }
         );
     }
@@ -288,21 +635,33 @@ mod tests {
     #[tokio::test]
     async fn test_index_files() {
         let path = "graph/pages/tests___parsing___files___basic.md";
-        let db = Meilisearch::new().await;
-        let files_index = db.client.index("files");
-        files_index.delete_all_documents().await.unwrap();
-        Indexer::new().await.index_files(path, false).await.unwrap();
-        let files = files_index.get_documents::<File>().await.unwrap().results;
+        let indexer = Indexer::new(MemoryBackend::new());
+        indexer.index_files(path, false).await.unwrap();
+        let files = indexer.db.search_files("", None, 20).await.unwrap();
         assert_eq!(files.len(), 1);
 
-        let file = files.get(0).unwrap();
+        let file = files.first().unwrap();
         assert_eq!(
             file,
             &File {
                 id: file.id.clone(),
                 path: path.to_string(),
                 title: "tests/parsing/files/basic".to_string(),
-                properties: HashMap::from([("foo".to_string(), "bar".to_string())]),
+                normalized_title: "tests/parsing/files/basic".to_string(),
+                aliases: vec![],
+                namespace_parent: Some("tests/parsing/files".to_string()),
+                namespace_path: vec![
+                    "tests".to_string(),
+                    "tests/parsing".to_string(),
+                    "tests/parsing/files".to_string()
+                ],
+                is_journal: false,
+                journal_day: None,
+                properties: HashMap::from([(
+                    "foo".to_string(),
+                    PropertyValue::Text("bar".to_string())
+                )]),
+                properties_span: file.properties_span,
                 wikilinks: vec!["wikilink".to_string()],
                 tags: vec![
                     "foo".to_string(),
@@ -310,7 +669,146 @@ mod tests {
                     "tag".to_string(),
                     "multi word tag".to_string()
                 ],
+                normalized_wikilinks: vec!["wikilink".to_string()],
+                normalized_tags: vec![
+                    "foo".to_string(),
+                    "bar".to_string(),
+                    "tag".to_string(),
+                    "multi word tag".to_string()
+                ],
+                block_refs: vec![],
+                backlinks: vec![],
+                backlink_count: 0,
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_index_graph_and_search() {
+        let indexer = Indexer::new(MemoryBackend::new());
+        indexer.index_files("graph/pages", true).await.unwrap();
+        let db = &indexer.db;
+        let tasks = db
+            .search_blocks("linter", Some("marker = TODO"), 20)
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(
+            tasks[0].content.lines().next(),
+            Some("- TODO [#A] Write the linter")
+        );
+        let file = db.get_file(&tasks[0].file_id).await.unwrap().unwrap();
+        assert_eq!(file.path, "graph/pages/tests___parsing___blocks___tasks.md");
+        let parsing = db
+            .search_files("", Some("namespace_path = \"tests/parsing\""), 20)
+            .await
+            .unwrap();
+        // Including the page with a top level code fence and rule
+        assert_eq!(parsing.len(), 8);
+        assert!(parsing
+            .iter()
+            .all(|f| f.title.starts_with("tests/parsing/")));
+
+        db.delete_blocks(std::slice::from_ref(&file.id))
+            .await
+            .unwrap();
+        let filter = format!("file_id = \"{}\"", file.id);
+        assert!(db
+            .search_blocks("", Some(&filter), 20)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_walk_errors_name_the_path() {
+        let mut walker = MdWalker::new("tests/graphs/missing");
+        let (path, _) = walker.next_path().unwrap().unwrap_err();
+        assert_eq!(path, PathBuf::from("tests/graphs/missing"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_files_are_reported() {
+        let root = "tests/graphs/broken";
+        let indexer = Indexer::new(MemoryBackend::new());
+        let report = indexer.index_files(root, true).await.unwrap();
+        assert_eq!(report.indexed, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "tests/graphs/broken/pages/not_utf8.md");
+        let files = indexer.db.search_files("", None, 20).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(report
+            .to_string()
+            .contains("1 files could not be indexed:\n  tests/graphs/broken/pages/not_utf8.md: "));
+
+        let strict = Indexer::new(MemoryBackend::new()).with_strict(true);
+        let error = strict.index_files(root, true).await.unwrap_err();
+        assert!(error.starts_with("tests/graphs/broken/pages/not_utf8.md: "));
+    }
+
+    #[tokio::test]
+    async fn test_batches() {
+        let indexer = Indexer::new(MemoryBackend::new());
+        indexer.index_files("graph/pages", true).await.unwrap();
+        let batched = Indexer::new(MemoryBackend::new())
+            .with_batch_size(2)
+            .with_concurrency(3);
+        let report = batched.index_files("graph/pages", true).await.unwrap();
+        assert_eq!(report.indexed, 14);
+        assert_eq!(
+            batched.db.search_files("", None, 20).await.unwrap().len(),
+            14
+        );
+        assert_eq!(
+            batched.db.search_blocks("", None, 1000).await.unwrap(),
+            indexer.db.search_blocks("", None, 1000).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backlinks() {
+        for index_blocks in [false, true] {
+            let root = std::env::temp_dir().join(format!(
+                "logseq-linter-backlinks-{}-{}",
+                std::process::id(),
+                index_blocks
+            ));
+            std::fs::create_dir_all(root.join("pages")).unwrap();
+            for name in ["alpha", "beta", "gamma", "lonely"] {
+                let fixture = format!("tests/graphs/links/pages/{}.md", name);
+                std::fs::copy(fixture, root.join("pages").join(format!("{}.md", name))).unwrap();
+            }
+            let indexer =
+                Indexer::new(MemoryBackend::new()).with_manifest(root.join("manifest.json"));
+            let beta = |files: Vec<File>| files.into_iter().next().unwrap();
+            indexer
+                .index_files(root.to_str().unwrap(), index_blocks)
+                .await
+                .unwrap();
+            let files = indexer.db.search_files("", Some("title = beta"), 1).await;
+            let file = beta(files.unwrap());
+            assert_eq!(file.backlinks, vec!["alpha", "gamma"]);
+            assert_eq!(file.backlink_count, 3);
+            let filter = format!("file_id = \"{}\"", file.id);
+            let beta_blocks = indexer.db.search_blocks("", Some(&filter), 20).await;
+            let beta_blocks = beta_blocks.unwrap().len();
+            assert_eq!(beta_blocks > 0, index_blocks);
+
+            // Only gamma changes, but beta loses a backlink
+            std::fs::write(root.join("pages/gamma.md"), "- No more links\n").unwrap();
+            let report = indexer
+                .index_files(root.to_str().unwrap(), index_blocks)
+                .await
+                .unwrap();
+            std::fs::remove_dir_all(&root).unwrap();
+            assert_eq!((report.indexed, report.unchanged), (1, 3));
+            let files = indexer.db.search_files("", Some("title = beta"), 1).await;
+            let file = beta(files.unwrap());
+            assert_eq!(file.backlinks, vec!["alpha"]);
+            assert_eq!(file.backlink_count, 2);
+            // Beta was updated for its backlinks, its blocks are untouched
+            let blocks = indexer.db.search_blocks("", Some(&filter), 20).await;
+            assert_eq!(blocks.unwrap().len(), beta_blocks);
+        }
+    }
 }
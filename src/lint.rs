@@ -0,0 +1,277 @@
+//! Lint rules that run over a parsed logseq graph and report diagnostics.
+
+pub mod alias_conflict;
+pub mod block_too_long;
+pub mod broken_wikilink;
+pub mod dangling_block_ref;
+pub mod dead_end_page;
+pub mod duplicate_block_id;
+pub mod empty_block;
+pub mod fix;
+pub mod format;
+pub mod journal_file_name;
+pub mod missing_namespace_parent;
+pub mod orphan_page;
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::graph::{Graph, Page};
+pub use crate::parsing::span::{Location, Span};
+use fix::Fix;
+
+/// How serious a diagnostic is.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by a rule.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The id of the rule that produced this diagnostic
+    pub rule_id: String,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// The path of the file the problem is in
+    pub path: String,
+    /// Where in the file the problem is
+    pub span: Span,
+    /// A human readable description of the problem
+    pub message: String,
+    /// How to fix the problem automatically, if it can be
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic for `rule` in `page`, using the rule's severity.
+    pub fn new(rule: &dyn Rule, page: &Page, span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            rule_id: rule.id().to_string(),
+            severity: rule.severity(),
+            path: page.file.path.clone(),
+            span,
+            message,
+            fix: None,
+        }
+    }
+
+    /// Attach a fix to the diagnostic.
+    pub fn with_fix(mut self, fix: Fix) -> Diagnostic {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}[{}]: {}",
+            self.path,
+            self.span.start.line,
+            self.span.start.column,
+            self.severity,
+            self.rule_id,
+            self.message
+        )
+    }
+}
+
+/// A lint rule.
+///
+/// Rules that only need to look at one page at a time implement `check_page`,
+/// rules that need the whole graph (for example to resolve links) override `check_graph`.
+pub trait Rule {
+    /// A unique, kebab-case id used to enable and disable the rule
+    fn id(&self) -> &'static str;
+
+    /// A short description of what the rule checks
+    fn description(&self) -> &'static str;
+
+    /// The severity of the diagnostics this rule produces
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Whether the rule runs unless it is explicitly enabled
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Check a single page.
+    fn check_page(&self, _graph: &Graph, _page: &Page) -> Vec<Diagnostic> {
+        vec![]
+    }
+
+    /// Check the whole graph. Defaults to checking every page.
+    fn check_graph(&self, graph: &Graph) -> Vec<Diagnostic> {
+        graph
+            .pages
+            .iter()
+            .flat_map(|page| self.check_page(graph, page))
+            .collect()
+    }
+}
+
+/// Every rule shipped with the linter.
+fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(alias_conflict::AliasConflict),
+        Box::new(block_too_long::BlockTooLong),
+        Box::new(broken_wikilink::BrokenWikilink),
+        Box::new(dangling_block_ref::DanglingBlockRef),
+        Box::new(dead_end_page::DeadEndPage),
+        Box::new(duplicate_block_id::DuplicateBlockId),
+        Box::new(empty_block::EmptyBlock),
+        Box::new(journal_file_name::JournalFileName),
+        Box::new(missing_namespace_parent::MissingNamespaceParent),
+        Box::new(orphan_page::OrphanPage),
+    ]
+}
+
+/// A set of rules, each of which can be turned on or off by id.
+pub struct Registry {
+    rules: Vec<Box<dyn Rule>>,
+    enabled: HashSet<&'static str>,
+}
+
+impl Default for Registry {
+    /// A registry with every builtin rule, enabled according to its default.
+    fn default() -> Registry {
+        let mut registry = Registry::new();
+        for rule in builtin_rules() {
+            registry.register(rule);
+        }
+        registry
+    }
+}
+
+impl Registry {
+    /// An empty registry.
+    pub fn new() -> Registry {
+        Registry {
+            rules: vec![],
+            enabled: HashSet::new(),
+        }
+    }
+
+    /// Add a rule, enabled according to `Rule::enabled_by_default`.
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        if rule.enabled_by_default() {
+            self.enabled.insert(rule.id());
+        }
+        self.rules.push(rule);
+    }
+
+    fn find(&self, id: &str) -> Result<&'static str, String> {
+        self.rules
+            .iter()
+            .map(|rule| rule.id())
+            .find(|rule_id| *rule_id == id)
+            .ok_or(format!("Unknown rule: {}", id))
+    }
+
+    /// Turn a rule on by id.
+    pub fn enable(&mut self, id: &str) -> Result<(), String> {
+        let id = self.find(id)?;
+        self.enabled.insert(id);
+        Ok(())
+    }
+
+    /// Turn a rule off by id.
+    pub fn disable(&mut self, id: &str) -> Result<(), String> {
+        let id = self.find(id)?;
+        self.enabled.remove(id);
+        Ok(())
+    }
+
+    /// Every registered rule, whether or not it is enabled.
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Whether the rule with this id will run.
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.enabled.contains(id)
+    }
+
+    /// Run every enabled rule over the graph. Diagnostics are sorted by path and position.
+    pub fn run(&self, graph: &Graph) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .rules
+            .iter()
+            .filter(|rule| self.is_enabled(rule.id()))
+            .flat_map(|rule| rule.check_graph(graph))
+            .collect();
+        diagnostics.sort_by(|a, b| {
+            (&a.path, a.span, &a.rule_id, &a.message)
+                .cmp(&(&b.path, b.span, &b.rule_id, &b.message))
+        });
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics = Registry::default().run(&graph);
+        let empty_blocks: Vec<String> = diagnostics
+            .iter()
+            .filter(|d| d.rule_id == "empty-block")
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            empty_blocks,
+            vec![
+                "graph/pages/tests___lint___empty_block.md:2:1: warning[empty-block]: Empty block",
+                "graph/pages/tests___lint___empty_block.md:4:3: warning[empty-block]: Empty block",
+            ]
+        );
+        assert!(diagnostics
+            .windows(2)
+            .all(|w| (&w[0].path, w[0].span) <= (&w[1].path, w[1].span)));
+    }
+
+    #[test]
+    fn test_disable() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let mut registry = Registry::default();
+        let ids: Vec<&str> = registry.rules().map(|rule| rule.id()).collect();
+        for id in ids {
+            registry.disable(id).unwrap();
+        }
+        assert!(!registry.is_enabled("empty-block"));
+        assert_eq!(registry.run(&graph), vec![]);
+        registry.enable("empty-block").unwrap();
+        assert_eq!(registry.run(&graph).len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_rule() {
+        assert_eq!(
+            Registry::default().disable("no-such-rule"),
+            Err("Unknown rule: no-such-rule".to_string())
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Reports aliases that can't all lead to the page that sets them.
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Span};
+use crate::parsing::property::PropertyValue;
+
+/// `alias::` entries that are claimed by another page too, that are another page's title,
+/// or that lead back to the page through other pages' aliases. Links to such an alias go to
+/// whichever page wins it, which is rarely obvious from the page the link is on.
+pub struct AliasConflict;
+
+impl AliasConflict {
+    /// The item of the page's `alias::` property that is `alias`, such as `JS` or
+    /// `[[JS]]` but not `JSX`, or the start of the page.
+    fn span(page: &Page, alias: &str) -> Span {
+        let mut offset = 0;
+        for line in page.content.split_inclusive('\n') {
+            let property = line.trim_start().trim_start_matches("- ");
+            if property.starts_with("alias::") {
+                let mut start = line.find("::").unwrap() + 2;
+                for item in PropertyValue::split_list(&line[start..]) {
+                    let trimmed = item.trim();
+                    if PropertyValue::parse(trimmed).as_strings() == [alias] {
+                        let from = offset + start + item.find(trimmed).unwrap_or(0);
+                        return Span::from_offsets(&page.content, from, from + trimmed.len());
+                    }
+                    start += item.len() + 1;
+                }
+            }
+            offset += line.len();
+        }
+        Span::from_offsets(&page.content, 0, 0)
+    }
+
+    /// The titles of the pages from `page` to `target` following aliases that are other
+    /// pages' titles, if there is such a path.
+    fn cycle<'a>(graph: &'a Graph, page: &'a Page, target: &Page) -> Option<Vec<&'a str>> {
+        let normalizer = graph.config.normalizer();
+        let mut path = vec![page.file.title.as_str()];
+        let mut stack = vec![(page, 0)];
+        let mut visited = vec![page.file.id.as_str()];
+        while let Some((current, next)) = stack.pop() {
+            let Some(alias) = current.file.aliases.get(next) else {
+                path.pop();
+                continue;
+            };
+            stack.push((current, next + 1));
+            let key = normalizer.normalize(alias);
+            let Some(titled) = graph.pages.iter().find(|p| p.file.normalized_title == key) else {
+                continue;
+            };
+            if titled.file.id == target.file.id {
+                path.push(titled.file.title.as_str());
+                return Some(path);
+            }
+            if !visited.contains(&titled.file.id.as_str()) {
+                visited.push(titled.file.id.as_str());
+                path.push(titled.file.title.as_str());
+                stack.push((titled, 0));
+            }
+        }
+        None
+    }
+}
+
+impl Rule for AliasConflict {
+    fn id(&self) -> &'static str {
+        "alias-conflict"
+    }
+
+    fn description(&self) -> &'static str {
+        "Aliases should lead to a single page"
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        let normalizer = graph.config.normalizer();
+        let mut diagnostics = vec![];
+        for alias in page.file.aliases.iter() {
+            let key = normalizer.normalize(alias);
+            if key == page.file.normalized_title {
+                continue;
+            }
+            let others = graph
+                .pages
+                .iter()
+                .filter(|other| other.file.id != page.file.id);
+            let mut message = None;
+            for other in others {
+                if other.file.normalized_title == key {
+                    message = Some(match Self::cycle(graph, other, page) {
+                        Some(path) => format!(
+                            "Aliases form a cycle: {} -> {}",
+                            page.file.title,
+                            path.join(" -> ")
+                        ),
+                        None => format!(
+                            "Alias \"{}\" is the title of another page, so links to it go there",
+                            alias
+                        ),
+                    });
+                    break;
+                }
+                if other
+                    .file
+                    .aliases
+                    .iter()
+                    .any(|a| normalizer.normalize(a) == key)
+                {
+                    message = Some(format!(
+                        "Alias \"{}\" is also claimed by \"{}\"",
+                        alias, other.file.title
+                    ));
+                }
+            }
+            if let Some(message) = message {
+                diagnostics.push(Diagnostic::new(
+                    self,
+                    page,
+                    Self::span(page, alias),
+                    message,
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_conflict() {
+        let graph = Graph::load("tests/graphs/aliases").unwrap();
+        let mut diagnostics: Vec<String> = AliasConflict
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        diagnostics.sort();
+        let path = "tests/graphs/aliases/pages";
+        assert_eq!(
+            diagnostics,
+            vec![
+                format!(
+                    "{path}/first.md:1:9: warning[alias-conflict]: \
+                    Aliases form a cycle: first -> second -> first"
+                ),
+                format!(
+                    "{path}/javascript.md:1:9: warning[alias-conflict]: \
+                    Alias \"JS\" is also claimed by \"typescript\""
+                ),
+                format!(
+                    "{path}/notes.md:1:9: warning[alias-conflict]: \
+                    Alias \"javascript\" is the title of another page, so links to it go there"
+                ),
+                format!(
+                    "{path}/second.md:1:9: warning[alias-conflict]: \
+                    Aliases form a cycle: second -> first -> second"
+                ),
+                format!(
+                    "{path}/typescript.md:1:14: warning[alias-conflict]: \
+                    Alias \"JS\" is also claimed by \"javascript\""
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! Reports blocks longer than the graph's `:block/content-max-length`.
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule};
+
+/// Blocks logseq refuses to edit because their content is over the configured
+/// maximum length.
+pub struct BlockTooLong;
+
+impl Rule for BlockTooLong {
+    fn id(&self) -> &'static str {
+        "block-too-long"
+    }
+
+    fn description(&self) -> &'static str {
+        "Blocks should be shorter than :block/content-max-length"
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        let max = graph.config.block_content_max_length;
+        page.blocks
+            .iter()
+            .filter(|block| block.content.chars().count() > max)
+            .map(|block| {
+                Diagnostic::new(
+                    self,
+                    page,
+                    block.span,
+                    format!(
+                        "Block is {} characters long, more than the maximum of {}",
+                        block.content.chars().count(),
+                        max
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_too_long() {
+        let mut graph = Graph::load("graph/pages").unwrap();
+        let page = graph.resolve("tests/parsing/blocks/hierarchy").unwrap();
+        assert!(BlockTooLong.check_page(&graph, page).is_empty());
+
+        graph.config.block_content_max_length = 6;
+        let page = graph.resolve("tests/parsing/blocks/hierarchy").unwrap();
+        let diagnostics = BlockTooLong.check_page(&graph, page);
+        let lines: Vec<usize> = diagnostics.iter().map(|d| d.span.start.line).collect();
+        assert_eq!(lines, vec![5, 6, 4], "Blocks are built children first");
+        assert_eq!(
+            diagnostics[0].message,
+            "Block is 7 characters long, more than the maximum of 6"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Reports links and tags to pages that do not exist.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Severity, Span};
+use crate::parsing::property::PropertyValue;
+use crate::parsing::reference::references;
+
+/// `[[links]]`, `#tags` and `tags::` entries that do not resolve to a page file.
+/// Logseq silently creates an empty page for each of these.
+pub struct BrokenWikilink;
+
+impl BrokenWikilink {
+    /// Byte ranges of every reference to `name` in `prose`, which has its code blanked out
+    /// so references in code are not found.
+    fn occurrences(prose: &str, name: &str) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = references(prose)
+            .into_iter()
+            .filter(|reference| reference.name == name)
+            .map(|reference| (reference.start, reference.end))
+            .collect();
+        if ranges.is_empty() {
+            // Entries in the `tags::` property are bare names, matched as whole list items
+            let mut offset = 0;
+            for line in prose.split_inclusive('\n') {
+                if line
+                    .trim_start()
+                    .trim_start_matches("- ")
+                    .starts_with("tags::")
+                {
+                    let mut start = offset + line.find("::").unwrap() + 2;
+                    for item in PropertyValue::split_list(&line[start - offset..]) {
+                        let trimmed = item.trim();
+                        if PropertyValue::parse(trimmed).as_strings() == [name] {
+                            let from = start + item.find(trimmed).unwrap_or(0);
+                            ranges.push((from, from + trimmed.len()));
+                        }
+                        start += item.len() + 1;
+                    }
+                }
+                offset += line.len();
+            }
+        }
+        ranges.sort();
+        ranges
+    }
+
+    /// The property key of the line `offset` is on, if it is a `key:: value` line.
+    fn property_key(content: &str, offset: usize) -> Option<&str> {
+        let start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = content[start..].trim_start().trim_start_matches("- ");
+        let (key, _) = line.split_once("::")?;
+        let valid = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        valid.then_some(key)
+    }
+
+    /// Normalized property keys used anywhere in the graph. With property pages on,
+    /// logseq gives each of these a page.
+    fn property_keys(graph: &Graph) -> HashSet<String> {
+        if !graph.config.property_pages_enabled {
+            return HashSet::new();
+        }
+        graph
+            .pages
+            .iter()
+            .flat_map(|page| {
+                page.file
+                    .properties
+                    .keys()
+                    .chain(page.blocks.iter().flat_map(|b| b.properties.keys()))
+            })
+            .map(|key| graph.links.key(key))
+            .collect()
+    }
+}
+
+impl Rule for BrokenWikilink {
+    fn id(&self) -> &'static str {
+        "broken-wikilink"
+    }
+
+    fn description(&self) -> &'static str {
+        "Links and tags should point at a page that exists"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        self.check_links(graph, page, &Self::property_keys(graph))
+    }
+
+    /// Collects the property keys once, rather than for every page.
+    fn check_graph(&self, graph: &Graph) -> Vec<Diagnostic> {
+        let property_keys = Self::property_keys(graph);
+        graph
+            .pages
+            .iter()
+            .flat_map(|page| self.check_links(graph, page, &property_keys))
+            .collect()
+    }
+}
+
+impl BrokenWikilink {
+    fn check_links(
+        &self,
+        graph: &Graph,
+        page: &Page,
+        property_keys: &HashSet<String>,
+    ) -> Vec<Diagnostic> {
+        let config = &graph.config;
+        let targets: BTreeSet<&String> = page
+            .file
+            .wikilinks
+            .iter()
+            .chain(page.file.tags.iter())
+            .filter(|name| graph.resolve(name).is_none())
+            .filter(|name| !property_keys.contains(&graph.links.key(name)))
+            .collect();
+        let mut diagnostics = vec![];
+        for name in targets {
+            let found = Self::occurrences(&page.prose, name);
+            // Values of ignored keys are plain text to logseq, not page references
+            let mut ranges: Vec<(usize, usize)> = found
+                .iter()
+                .copied()
+                .filter(|(start, _)| {
+                    !Self::property_key(&page.content, *start)
+                        .is_some_and(|key| config.ignored_page_references_keywords.contains(key))
+                })
+                .collect();
+            if !found.is_empty() && ranges.is_empty() {
+                continue;
+            }
+            if ranges.is_empty() {
+                ranges.push((0, 0));
+            }
+            for (start, end) in ranges {
+                diagnostics.push(Diagnostic::new(
+                    self,
+                    page,
+                    Span::from_offsets(&page.content, start, end),
+                    format!("Link to missing page \"{}\"", name),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_wikilink() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let page = graph
+            .resolve("tests/parsing/files/basic")
+            .expect("The fixture page should exist");
+        let diagnostics: Vec<String> = BrokenWikilink
+            .check_page(&graph, page)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        let path = "graph/pages/tests___parsing___files___basic.md";
+        // The `foo` tag is not reported because `foo::` is a property, which has a page
+        assert_eq!(
+            diagnostics,
+            vec![
+                format!("{path}:1:13: error[broken-wikilink]: Link to missing page \"bar\""),
+                format!(
+                    "{path}:4:44: error[broken-wikilink]: Link to missing page \"multi word tag\""
+                ),
+                format!("{path}:4:35: error[broken-wikilink]: Link to missing page \"tag\""),
+                format!("{path}:4:18: error[broken-wikilink]: Link to missing page \"wikilink\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_to_existing_pages_are_not_reported() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let page = graph.resolve("tests/lint/broken_wikilink").unwrap();
+        let diagnostics = BrokenWikilink.check_page(&graph, page);
+        // The same link in code on line 4 is not reported
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.start.line, 3);
+        assert_eq!(diagnostics[0].message, "Link to missing page \"contnets\"");
+    }
+
+    #[test]
+    fn test_config() {
+        let mut graph = Graph::load("graph/pages").unwrap();
+        let page = graph.resolve("tests/lint/broken_wikilink_config").unwrap();
+        assert_eq!(BrokenWikilink.check_page(&graph, page).len(), 2);
+
+        graph
+            .config
+            .ignored_page_references_keywords
+            .insert("author".to_string());
+        let page = graph.resolve("tests/lint/broken_wikilink_config").unwrap();
+        let diagnostics = BrokenWikilink.check_page(&graph, page);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Link to missing page \"nowhere\"");
+
+        graph.config.property_pages_enabled = false;
+        let page = graph.resolve("tests/lint/broken_wikilink_config").unwrap();
+        let messages: Vec<String> = BrokenWikilink
+            .check_page(&graph, page)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Link to missing page \"nowhere\"",
+                "Link to missing page \"status\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_property_key() {
+        let content = "author:: [[Jane]]\n- status:: [[x]]\ntext [[y]]";
+        assert_eq!(BrokenWikilink::property_key(content, 9), Some("author"));
+        assert_eq!(BrokenWikilink::property_key(content, 30), Some("status"));
+        assert_eq!(BrokenWikilink::property_key(content, 41), None);
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let content = "#tag #tags [[tag]] #[[tag]] [x]([[tag]]) [[a [[tag]]]]";
+        assert_eq!(
+            BrokenWikilink::occurrences(content, "tag"),
+            vec![(0, 4), (11, 18), (19, 27), (28, 40), (45, 52)]
+        );
+        // Bare `tags::` entries match whole items, not any text containing the name
+        assert_eq!(
+            BrokenWikilink::occurrences("tags:: foobar, foo\n", "foo"),
+            vec![(15, 18)]
+        );
+        assert_eq!(
+            BrokenWikilink::occurrences("- tags:: foo bar, foo\n", "foo bar"),
+            vec![(9, 16)]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Reports block references to blocks that do not exist.
+
+use std::collections::HashSet;
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Severity, Span};
+
+/// `((uuid))` references and embeds whose uuid is not the `id::` of any block in the graph.
+/// These usually appear after a merge conflict drops the referenced block.
+pub struct DanglingBlockRef;
+
+impl Rule for DanglingBlockRef {
+    fn id(&self) -> &'static str {
+        "dangling-block-ref"
+    }
+
+    fn description(&self) -> &'static str {
+        "Block references should point at a block with that id"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_graph(&self, graph: &Graph) -> Vec<Diagnostic> {
+        let ids: HashSet<String> = graph
+            .pages
+            .iter()
+            .flat_map(|page| page.blocks.iter())
+            .filter(|block| block.id_span.is_some())
+            .map(|block| block.id.clone())
+            .collect();
+        graph
+            .pages
+            .iter()
+            .flat_map(|page| self.check_refs(page, &ids))
+            .collect()
+    }
+}
+
+impl DanglingBlockRef {
+    fn check_refs(&self, page: &Page, ids: &HashSet<String>) -> Vec<Diagnostic> {
+        let dangling: HashSet<&String> = page
+            .file
+            .block_refs
+            .iter()
+            .filter(|id| !ids.contains(*id))
+            .collect();
+        let mut diagnostics = vec![];
+        for id in dangling {
+            let reference = format!("(({}))", id);
+            // In the prose, so a reference shown in code is not reported
+            for (start, _) in page.prose.match_indices(&reference) {
+                diagnostics.push(Diagnostic::new(
+                    self,
+                    page,
+                    Span::from_offsets(&page.content, start, start + reference.len()),
+                    format!("Reference to missing block {}", id),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dangling_block_ref() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics: Vec<String> = DanglingBlockRef
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "graph/pages/tests___lint___block_refs.md:3:47: error[dangling-block-ref]: \
+                Reference to missing block 00000000-0000-4000-8000-000000000000"
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Reports pages that have content but link nowhere.
+
+use crate::graph::links::Node;
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Severity, Span};
+use crate::parsing::property::parse_properties;
+
+/// Pages with content that don't link, tag, reference or embed any other page, so
+/// reading them leads nowhere. Empty pages are left to the orphan report. Off by
+/// default, like `orphan-page`.
+pub struct DeadEndPage;
+
+impl DeadEndPage {
+    /// Whether the page has no text besides list markers and properties. Paragraphs count,
+    /// not only blocks.
+    fn is_empty(page: &Page) -> bool {
+        page.prose.lines().all(|line| {
+            let text = line.trim().trim_start_matches('-').trim();
+            text.is_empty() || !parse_properties(text, &Default::default()).is_empty()
+        })
+    }
+
+    /// Whether anything on the page, or any of its blocks, leads to another page.
+    fn links_out(graph: &Graph, page: &Page) -> bool {
+        let key = graph.links.key(&page.file.title);
+        let nodes = std::iter::once(graph.links.page(&page.file.title)).chain(
+            page.blocks
+                .iter()
+                .map(|block| Node::Block(block.id.clone())),
+        );
+        nodes.into_iter().any(|node| {
+            graph
+                .links
+                .outgoing(&node)
+                .any(|edge| graph.links.page_of(&edge.target) != Some(key.as_str()))
+        })
+    }
+}
+
+impl Rule for DeadEndPage {
+    fn id(&self) -> &'static str {
+        "dead-end-page"
+    }
+
+    fn description(&self) -> &'static str {
+        "Pages with content should link to another page"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        if Self::is_empty(page)
+            || graph.config.gardening.excludes(&page.file)
+            || Self::links_out(graph, page)
+        {
+            return vec![];
+        }
+        vec![Diagnostic::new(
+            self,
+            page,
+            Span::from_offsets(&page.content, 0, 0),
+            format!("\"{}\" does not link to any other page", page.file.title),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_end_page() {
+        let mut graph = Graph::load("tests/graphs/links").unwrap();
+        let dead_ends: Vec<String> = DeadEndPage
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            dead_ends,
+            vec![
+                "tests/graphs/links/pages/lonely.md:1:1: info[dead-end-page]: \
+                \"lonely\" does not link to any other page",
+                // Paragraphs are content too, though they are not blocks
+                "tests/graphs/links/pages/paragraph.md:1:1: info[dead-end-page]: \
+                \"paragraph\" does not link to any other page",
+            ]
+        );
+
+        graph.config.gardening.exclude_journals = false;
+        let dead_ends: Vec<String> = DeadEndPage
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.path.clone())
+            .filter(|path| path.contains("journals"))
+            .collect();
+        assert_eq!(dead_ends, vec!["tests/graphs/links/journals/2024_05_01.md"]);
+    }
+}
@@ -0,0 +1,199 @@
+//! Reports block ids that are used by more than one block.
+
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use crate::graph::{Graph, Page};
+use crate::lint::fix::Fix;
+use crate::lint::{Diagnostic, Location, Rule, Severity, Span};
+use crate::parsing::ID_NAMESPACE;
+
+/// Two blocks with the same `id::`, usually from copy-pasting a block between pages.
+/// Logseq then shows either block wherever the id is referenced.
+pub struct DuplicateBlockId;
+
+/// One block's `id::` property.
+struct Occurrence<'a> {
+    page: &'a Page,
+    /// The byte range of the uuid in the page content
+    start: usize,
+    end: usize,
+}
+
+impl DuplicateBlockId {
+    /// Every `id::` in the graph, grouped by uuid, in path order.
+    fn occurrences(graph: &Graph) -> BTreeMap<&str, Vec<Occurrence<'_>>> {
+        let mut pages: Vec<&Page> = graph.pages.iter().collect();
+        pages.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+        let mut occurrences: BTreeMap<&str, Vec<Occurrence>> = BTreeMap::new();
+        for page in pages {
+            for block in page.blocks.iter() {
+                let Some(span) = &block.id_span else {
+                    continue;
+                };
+                occurrences
+                    .entry(block.id.as_str())
+                    .or_default()
+                    .push(Occurrence {
+                        page,
+                        start: span.start.offset,
+                        end: span.end.offset,
+                    });
+            }
+        }
+        occurrences.retain(|_, occurrences| occurrences.len() > 1);
+        occurrences
+    }
+
+    /// The copy that keeps the id. `((uuid))` references can't tell the copies apart, so
+    /// it is the copy on the oldest journal, or else the first one in path order.
+    fn original(occurrences: &[Occurrence]) -> usize {
+        (0..occurrences.len())
+            .min_by_key(|i| {
+                let day = occurrences[*i].page.file.journal_day;
+                (day.is_none(), day)
+            })
+            .unwrap_or(0)
+    }
+
+    /// A fresh id for the copy of `id` at `occurrence`. It is derived from where the copy
+    /// is, so the same fix is printed by a dry run and applied later.
+    fn fresh_id(id: &str, occurrence: &Occurrence) -> String {
+        let name = format!("{}:{}:{}", occurrence.page.file.path, occurrence.start, id);
+        Uuid::new_v5(&ID_NAMESPACE, name.as_bytes()).to_string()
+    }
+}
+
+impl Rule for DuplicateBlockId {
+    fn id(&self) -> &'static str {
+        "duplicate-block-id"
+    }
+
+    fn description(&self) -> &'static str {
+        "Block ids should be unique across the graph"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// The copies are fixed by giving them fresh ids, all but the original which keeps its
+    /// id, so references to it still work.
+    fn check_graph(&self, graph: &Graph) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        for (id, occurrences) in Self::occurrences(graph) {
+            let original = Self::original(&occurrences);
+            let locations: Vec<(String, Location)> = occurrences
+                .iter()
+                .map(|o| {
+                    let location = Location::from_offset(&o.page.content, o.start);
+                    (o.page.file.path.clone(), location)
+                })
+                .collect();
+            for (i, occurrence) in occurrences.iter().enumerate() {
+                let others: Vec<String> = locations
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, (path, location))| format!("{}:{}", path, location.line))
+                    .collect();
+                let mut diagnostic = Diagnostic::new(
+                    self,
+                    occurrence.page,
+                    Span::from_offsets(&occurrence.page.content, occurrence.start, occurrence.end),
+                    format!("Block id {} is also used at {}", id, others.join(", ")),
+                );
+                if i != original {
+                    diagnostic = diagnostic.with_fix(Fix::replace(
+                        "Give the block a new id",
+                        occurrence.start,
+                        occurrence.end,
+                        Self::fresh_id(id, occurrence),
+                    ));
+                }
+                diagnostics.push(diagnostic);
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::fix;
+
+    #[test]
+    fn test_duplicate_block_id() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics: Vec<String> = DuplicateBlockId
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        let id = "662ef9e2-4b89-4f7d-9a54-afd395b03cb0";
+        let copy = "graph/pages/tests___lint___duplicate_block_id.md";
+        let original = "graph/pages/tests___parsing___blocks___property.md";
+        assert_eq!(
+            diagnostics,
+            vec![
+                format!("{copy}:3:8: error[duplicate-block-id]: Block id {id} is also used at {copy}:5, {original}:3"),
+                format!("{copy}:5:8: error[duplicate-block-id]: Block id {id} is also used at {copy}:3, {original}:3"),
+                format!("{original}:3:8: error[duplicate-block-id]: Block id {id} is also used at {copy}:3, {copy}:5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics = DuplicateBlockId.check_graph(&graph);
+        let fixed = fix::fix_graph(&graph, &diagnostics).unwrap();
+        // Neither page is a journal, so the first copy in path order keeps the id
+        assert_eq!(fixed.len(), 2);
+        let file = &fixed[0];
+        assert_eq!(
+            file.path,
+            "graph/pages/tests___lint___duplicate_block_id.md"
+        );
+        let lines: Vec<&str> = file.fixed.lines().collect();
+        assert_eq!(lines[2], "  id:: 662ef9e2-4b89-4f7d-9a54-afd395b03cb0");
+        assert_ne!(lines[4], "  id:: 662ef9e2-4b89-4f7d-9a54-afd395b03cb0");
+        assert!(Uuid::parse_str(lines[4].trim_start_matches("  id:: ")).is_ok());
+        let file = &fixed[1];
+        assert_eq!(
+            file.path,
+            "graph/pages/tests___parsing___blocks___property.md"
+        );
+        assert!(!file.fixed.contains("662ef9e2-4b89-4f7d-9a54-afd395b03cb0"));
+        assert_eq!(
+            fixed,
+            fix::fix_graph(&graph, &diagnostics).unwrap(),
+            "Fixes are the same every time"
+        );
+    }
+
+    #[test]
+    fn test_original() {
+        let graph = Graph::load("tests/graphs/block_ids").unwrap();
+        let fixes: Vec<(String, bool)> = DuplicateBlockId
+            .check_graph(&graph)
+            .iter()
+            .map(|d| (d.path.clone(), d.fix.is_some()))
+            .collect();
+        let path = "tests/graphs/block_ids";
+        assert_eq!(
+            fixes,
+            vec![
+                // The oldest journal keeps the id, though it is not first in path order
+                (format!("{path}/journals/01_06_2024.md"), true),
+                (format!("{path}/journals/30_05_2024.md"), false),
+                (format!("{path}/pages/copied.md"), true),
+                // Then the first copy in the page
+                (format!("{path}/pages/twice.md"), false),
+                (format!("{path}/pages/twice.md"), true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Reports blocks that have no content.
+
+use regex::Regex;
+
+use crate::graph::{Graph, Page};
+use crate::lint::fix::Fix;
+use crate::lint::{Diagnostic, Rule, Span};
+
+/// Blocks that are only a list marker. A page whose only block is empty is
+/// logseq's placeholder for a new page, so it is not reported.
+pub struct EmptyBlock;
+
+impl EmptyBlock {
+    /// Remove the empty block's line, unless the block has children that would move to
+    /// another parent.
+    fn fix(content: &str, line_start: usize, line_end: usize) -> Option<Fix> {
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let line = &content[line_start..line_end];
+        let rest = content[line_end..].strip_prefix('\n');
+        let next = rest.and_then(|rest| rest.lines().find(|l| !l.trim().is_empty()));
+        if next.is_some_and(|next| indent(next) > indent(line)) {
+            return None;
+        }
+        let (start, end) = match rest {
+            Some(_) => (line_start, line_end + 1),
+            // The last line has no newline of its own, so remove the one before it
+            None => (line_start.saturating_sub(1), line_end),
+        };
+        Some(Fix::replace(
+            "Remove the empty block",
+            start,
+            end,
+            String::new(),
+        ))
+    }
+}
+
+impl Rule for EmptyBlock {
+    fn id(&self) -> &'static str {
+        "empty-block"
+    }
+
+    fn description(&self) -> &'static str {
+        "Blocks should have content"
+    }
+
+    fn check_page(&self, _graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        if page.blocks.len() <= 1 {
+            return vec![];
+        }
+        let re = Regex::new(r"(?m)^[ \t]*(-)[ \t]*$").unwrap();
+        // In the prose, so a `-` line in a code block is not a block
+        re.captures_iter(&page.prose)
+            .map(|captures| {
+                let line = captures.get(0).unwrap();
+                let marker = captures.get(1).unwrap();
+                let diagnostic = Diagnostic::new(
+                    self,
+                    page,
+                    Span::from_offsets(&page.content, marker.start(), marker.end()),
+                    "Empty block".to_string(),
+                );
+                match Self::fix(&page.content, line.start(), line.end()) {
+                    Some(fix) => diagnostic.with_fix(fix),
+                    None => diagnostic,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_block() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics: Vec<String> = graph
+            .pages
+            .iter()
+            .flat_map(|page| EmptyBlock.check_page(&graph, page))
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "graph/pages/tests___lint___empty_block.md:2:1: warning[empty-block]: Empty block",
+                "graph/pages/tests___lint___empty_block.md:4:3: warning[empty-block]: Empty block",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let content = "- a\n-\n  - child\n- b\n  -";
+        assert_eq!(
+            EmptyBlock::fix(content, 4, 5),
+            None,
+            "The block has a child"
+        );
+        let fix = EmptyBlock::fix(content, 20, 23).unwrap();
+        assert_eq!((fix.edits[0].start, fix.edits[0].end), (19, 23));
+    }
+}
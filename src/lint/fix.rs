@@ -0,0 +1,214 @@
+//! Mechanical fixes that rules attach to their diagnostics, and applying them to files.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::graph::Graph;
+use crate::lint::Diagnostic;
+
+/// Replace a byte range of a file's original content.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Edit {
+    fn overlaps(&self, other: &Edit) -> bool {
+        // Two insertions at the same place overlap too, their order would be ambiguous
+        (self.start < other.end && other.start < self.end) || self.start == other.start
+    }
+}
+
+/// A change that resolves a diagnostic. Its edits are applied together or not at all.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// What the fix does, e.g. "Give the block a new id"
+    pub description: String,
+    pub edits: Vec<Edit>,
+}
+
+impl Fix {
+    /// A fix with a single edit.
+    pub fn replace(description: &str, start: usize, end: usize, replacement: String) -> Fix {
+        Fix {
+            description: description.to_string(),
+            edits: vec![Edit {
+                start,
+                end,
+                replacement,
+            }],
+        }
+    }
+}
+
+/// The outcome of fixing one file.
+#[derive(Debug, PartialEq)]
+pub struct FixedFile {
+    pub path: String,
+    pub original: String,
+    pub fixed: String,
+    /// The number of fixes that were applied
+    pub applied: usize,
+    /// The number of fixes that were skipped because they overlap an applied one.
+    /// Fixing again after writing the file picks them up.
+    pub skipped: usize,
+}
+
+impl FixedFile {
+    /// A unified diff from the original to the fixed content.
+    pub fn diff(&self) -> String {
+        let path = self.path.trim_start_matches('/');
+        TextDiff::from_lines(&self.original, &self.fixed)
+            .unified_diff()
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string()
+    }
+
+    /// Write the fixed content, unless the file changed since it was read.
+    /// The content is written to a temporary file first so a failed write can't truncate the page.
+    pub fn write(&self) -> Result<(), String> {
+        let current = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        if current != self.original {
+            return Err(format!("{} changed since it was read", self.path));
+        }
+        let temporary = format!("{}.fix", self.path);
+        std::fs::write(&temporary, &self.fixed).map_err(|e| e.to_string())?;
+        std::fs::rename(&temporary, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// Apply as many `fixes` to `content` as possible. Fixes are tried in order and one that
+/// overlaps an earlier fix is skipped. Returns the new content and how many fixes were applied.
+pub fn apply(content: &str, fixes: &[&Fix]) -> Result<(String, usize), String> {
+    let mut accepted: Vec<&Edit> = vec![];
+    let mut applied = 0;
+    for fix in fixes {
+        for edit in fix.edits.iter() {
+            let valid = edit.start <= edit.end
+                && content.is_char_boundary(edit.start)
+                && content.is_char_boundary(edit.end);
+            if !valid {
+                return Err(format!(
+                    "Invalid edit {}..{} in \"{}\"",
+                    edit.start, edit.end, fix.description
+                ));
+            }
+        }
+        let overlapping = fix.edits.iter().enumerate().any(|(i, edit)| {
+            accepted.iter().any(|other| edit.overlaps(other))
+                || fix.edits[..i].iter().any(|other| edit.overlaps(other))
+        });
+        if !overlapping {
+            accepted.extend(fix.edits.iter());
+            applied += 1;
+        }
+    }
+    // Replace from the end so earlier offsets stay valid
+    accepted.sort_by_key(|edit| edit.start);
+    let mut fixed = content.to_string();
+    for edit in accepted.into_iter().rev() {
+        fixed.replace_range(edit.start..edit.end, &edit.replacement);
+    }
+    Ok((fixed, applied))
+}
+
+/// Apply the fixes of `diagnostics` to the pages of `graph`, without writing anything.
+/// Only files that change are returned, in path order.
+pub fn fix_graph(graph: &Graph, diagnostics: &[Diagnostic]) -> Result<Vec<FixedFile>, String> {
+    let mut fixes: BTreeMap<&str, Vec<&Fix>> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        if let Some(fix) = &diagnostic.fix {
+            fixes.entry(diagnostic.path.as_str()).or_default().push(fix);
+        }
+    }
+    let mut fixed_files = vec![];
+    for (path, fixes) in fixes {
+        let page = graph
+            .pages
+            .iter()
+            .find(|page| page.file.path == path)
+            .ok_or(format!("No page at {}", path))?;
+        let (fixed, applied) = apply(&page.content, &fixes)?;
+        if fixed == page.content {
+            continue;
+        }
+        fixed_files.push(FixedFile {
+            path: path.to_string(),
+            original: page.content.clone(),
+            fixed,
+            applied,
+            skipped: fixes.len() - applied,
+        });
+    }
+    Ok(fixed_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::Registry;
+
+    #[test]
+    fn test_apply() {
+        let content = "foo bar baz";
+        let first = Fix::replace("first", 0, 3, "one".to_string());
+        let second = Fix::replace("second", 8, 11, "three".to_string());
+        let (fixed, applied) = apply(content, &[&second, &first]).unwrap();
+        assert_eq!(fixed, "one bar three");
+        assert_eq!(applied, 2);
+    }
+
+    #[test]
+    fn test_overlapping_fixes_are_skipped() {
+        let content = "foo bar baz";
+        let first = Fix::replace("first", 0, 5, "".to_string());
+        let second = Fix::replace("second", 4, 7, "BAR".to_string());
+        let insert = Fix::replace("insert", 11, 11, "!".to_string());
+        let other_insert = Fix::replace("other insert", 11, 11, "?".to_string());
+        let (fixed, applied) = apply(content, &[&first, &second, &insert, &other_insert]).unwrap();
+        assert_eq!(fixed, "ar baz!");
+        assert_eq!(applied, 2);
+    }
+
+    #[test]
+    fn test_invalid_edit() {
+        let fix = Fix::replace("bad", 1, 2, "".to_string());
+        assert!(apply("ä", &[&fix]).is_err());
+        let fix = Fix::replace("bad", 0, 9, "".to_string());
+        assert!(apply("ä", &[&fix]).is_err());
+    }
+
+    #[test]
+    fn test_fix_graph() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let diagnostics = Registry::default().run(&graph);
+        let fixed = fix_graph(&graph, &diagnostics).unwrap();
+        let fixed = fixed
+            .iter()
+            .find(|f| f.path == "graph/pages/tests___lint___empty_block.md")
+            .unwrap();
+        assert_eq!(
+            fixed.fixed,
+            "- This page has empty blocks\n- Including nested ones\n\
+             - But not a dash in code\n  ```\n  -\n  ```\n"
+        );
+        assert_eq!((fixed.applied, fixed.skipped), (2, 0));
+        assert_eq!(
+            fixed.diff(),
+            "--- a/graph/pages/tests___lint___empty_block.md\n\
+             +++ b/graph/pages/tests___lint___empty_block.md\n\
+             @@ -1,7 +1,5 @@\n \
+             - This page has empty blocks\n\
+             --\n \
+             - Including nested ones\n\
+             -  -\n \
+             - But not a dash in code\n   \
+             ```\n   \
+             -\n"
+        );
+    }
+}
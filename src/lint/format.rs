@@ -0,0 +1,204 @@
+//! Renders diagnostics for people and for other tools.
+
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::graph::Graph;
+use crate::lint::{Diagnostic, Registry, Severity};
+
+/// How diagnostics are printed.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One line per diagnostic followed by the source it points at
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning dashboards
+    Sarif,
+    /// GitHub Actions workflow commands, which annotate the lines in a pull request
+    Github,
+}
+
+impl Format {
+    /// Render every diagnostic. `graph` provides the source for snippets and
+    /// `registry` the descriptions of the rules that ran.
+    pub fn render(self, diagnostics: &[Diagnostic], graph: &Graph, registry: &Registry) -> String {
+        match self {
+            Format::Text => diagnostics
+                .iter()
+                .map(|d| format!("{}\n{}\n", d, Self::snippet(d, graph)))
+                .collect(),
+            Format::Json => diagnostics
+                .iter()
+                .map(|d| serde_json::to_string(d).unwrap() + "\n")
+                .collect(),
+            Format::Sarif => {
+                serde_json::to_string_pretty(&Self::sarif(diagnostics, registry)).unwrap() + "\n"
+            }
+            Format::Github => diagnostics.iter().map(Self::github).collect(),
+        }
+    }
+
+    /// The line a diagnostic starts on, with its span underlined.
+    fn snippet(diagnostic: &Diagnostic, graph: &Graph) -> String {
+        let Some(page) = graph.pages.iter().find(|p| p.file.path == diagnostic.path) else {
+            return String::new();
+        };
+        let start = diagnostic.span.start;
+        let end = diagnostic.span.end;
+        let line = page.content.lines().nth(start.line - 1).unwrap_or("");
+        let width = if end.line == start.line {
+            end.column.saturating_sub(start.column)
+        } else {
+            line.chars().count().saturating_sub(start.column - 1)
+        };
+        let number = start.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "{gutter} |\n{number} | {line}\n{gutter} | {}{}\n",
+            " ".repeat(start.column - 1),
+            "^".repeat(width.max(1))
+        )
+    }
+
+    fn sarif_level(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Info => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    fn sarif(diagnostics: &[Diagnostic], registry: &Registry) -> serde_json::Value {
+        let rules: Vec<serde_json::Value> = registry
+            .rules()
+            .filter(|rule| registry.is_enabled(rule.id()))
+            .map(|rule| {
+                json!({
+                    "id": rule.id(),
+                    "shortDescription": { "text": rule.description() },
+                    "defaultConfiguration": { "level": Self::sarif_level(rule.severity()) },
+                })
+            })
+            .collect();
+        let results: Vec<serde_json::Value> = diagnostics
+            .iter()
+            .map(|d| {
+                json!({
+                    "ruleId": d.rule_id,
+                    "level": Self::sarif_level(d.severity),
+                    "message": { "text": d.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": d.path },
+                            "region": {
+                                "startLine": d.span.start.line,
+                                "startColumn": d.span.start.column,
+                                "endLine": d.span.end.line,
+                                "endColumn": d.span.end.column,
+                            },
+                        },
+                    }],
+                })
+            })
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "columnKind": "unicodeCodePoints",
+                "results": results,
+            }],
+        })
+    }
+
+    /// Escape a workflow command's message. Properties also need `:` and `,` escaped.
+    fn github_escape(text: &str, property: bool) -> String {
+        let text = text
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A");
+        if property {
+            text.replace(':', "%3A").replace(',', "%2C")
+        } else {
+            text
+        }
+    }
+
+    fn github(diagnostic: &Diagnostic) -> String {
+        let command = match diagnostic.severity {
+            Severity::Info => "notice",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        format!(
+            "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}\n",
+            command,
+            Self::github_escape(&diagnostic.path, true),
+            diagnostic.span.start.line,
+            diagnostic.span.start.column,
+            diagnostic.span.end.line,
+            diagnostic.span.end.column,
+            Self::github_escape(&diagnostic.rule_id, true),
+            Self::github_escape(&diagnostic.message, false)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compare `format`'s output for the fixture graph with `tests/golden/lint.<extension>`.
+    /// Set `UPDATE_GOLDEN=1` to rewrite the golden file instead.
+    fn check_golden(format: Format, extension: &str) {
+        let graph = Graph::load("graph/pages").unwrap();
+        let registry = Registry::default();
+        let diagnostics = registry.run(&graph);
+        let output = format.render(&diagnostics, &graph, &registry);
+        let path = format!("tests/golden/lint.{}", extension);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &output).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            output, expected,
+            "Run with UPDATE_GOLDEN=1 to update {}",
+            path
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        check_golden(Format::Text, "txt");
+    }
+
+    #[test]
+    fn test_json() {
+        check_golden(Format::Json, "jsonl");
+    }
+
+    #[test]
+    fn test_sarif() {
+        check_golden(Format::Sarif, "sarif");
+    }
+
+    #[test]
+    fn test_github() {
+        check_golden(Format::Github, "github");
+    }
+
+    #[test]
+    fn test_github_escape() {
+        assert_eq!(Format::github_escape("a: 50%\nb", false), "a: 50%25%0Ab");
+        assert_eq!(Format::github_escape("a:b,c", true), "a%3Ab%2Cc");
+    }
+}
@@ -0,0 +1,55 @@
+//! Reports journal files whose name is not a date in the journal file name format.
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Span};
+
+/// Journal files that logseq can't turn into a date, so they show up as a plain
+/// page instead of in the journals view.
+pub struct JournalFileName;
+
+impl Rule for JournalFileName {
+    fn id(&self) -> &'static str {
+        "journal-file-name"
+    }
+
+    fn description(&self) -> &'static str {
+        "Journal file names should match the journal file name format"
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        if !page.file.is_journal || page.file.journal_day.is_some() {
+            return vec![];
+        }
+        vec![Diagnostic::new(
+            self,
+            page,
+            Span::from_offsets(&page.content, 0, 0),
+            format!(
+                "Journal file name \"{}\" does not match the format \"{}\"",
+                page.file.title, graph.config.journal_file_name_format
+            ),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_file_name() {
+        let graph = Graph::load("graph/journals").unwrap();
+        let diagnostics: Vec<String> = JournalFileName
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "graph/journals/2024-04-29.md:1:1: warning[journal-file-name]: \
+                Journal file name \"2024-04-29\" does not match the format \"yyyy_MM_dd\""
+            ]
+        );
+    }
+}
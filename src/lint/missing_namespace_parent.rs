@@ -0,0 +1,126 @@
+//! Reports namespaced pages whose namespaces have no page of their own.
+
+use crate::graph::namespaces::Namespace;
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Span};
+
+/// Pages such as `a/b/c` when there is no `a/b` or `a` page, or the `a/b` page is titled `A/b`.
+/// Logseq creates the missing page and keeps the casing of whichever was seen first, so
+/// the namespace shows up differently from how it was written. Off by default, many
+/// graphs leave their namespaces without pages on purpose.
+pub struct MissingNamespaceParent;
+
+impl Rule for MissingNamespaceParent {
+    fn id(&self) -> &'static str {
+        "missing-namespace-parent"
+    }
+
+    fn description(&self) -> &'static str {
+        "The namespace of a namespaced page should have a page, titled the same way"
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    /// Walks the namespace tree from its roots. A namespace without a page is reported on
+    /// the nearest pages under it, a differently titled one on the pages directly under it.
+    fn check_graph(&self, graph: &Graph) -> Vec<Diagnostic> {
+        let tree = &graph.namespaces;
+        let mut diagnostics = vec![];
+        let mut stack = tree.roots();
+        while let Some(namespace) = stack.pop() {
+            let children = tree.children(&namespace.name);
+            stack.extend(children.iter().copied());
+            if children.is_empty() {
+                continue;
+            }
+            let key = graph.links.key(&namespace.name);
+            match &namespace.title {
+                None => {
+                    for page in Self::nearest_pages(graph, namespace) {
+                        // Named the way the page wrote it
+                        let name = page
+                            .file
+                            .namespace_path
+                            .iter()
+                            .find(|name| graph.links.key(name) == key)
+                            .unwrap_or(&namespace.name);
+                        let message = format!("Namespace \"{}\" has no page", name);
+                        diagnostics.push(self.diagnostic(page, message));
+                    }
+                }
+                Some(title) => {
+                    let pages = children
+                        .iter()
+                        .filter_map(|child| graph.resolve(child.title.as_ref()?));
+                    for page in pages {
+                        let Some(parent) = &page.file.namespace_parent else {
+                            continue;
+                        };
+                        if parent != title {
+                            let message =
+                                format!("Namespace \"{}\" is titled \"{}\"", parent, title);
+                            diagnostics.push(self.diagnostic(page, message));
+                        }
+                    }
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+impl MissingNamespaceParent {
+    /// The pages under `namespace` that have no other page between them and it.
+    fn nearest_pages<'a>(graph: &'a Graph, namespace: &Namespace) -> Vec<&'a Page> {
+        let mut pages = vec![];
+        let mut stack = graph.namespaces.children(&namespace.name);
+        while let Some(child) = stack.pop() {
+            match &child.title {
+                Some(title) => pages.extend(graph.resolve(title)),
+                None => stack.extend(graph.namespaces.children(&child.name)),
+            }
+        }
+        pages
+    }
+
+    fn diagnostic(&self, page: &Page, message: String) -> Diagnostic {
+        Diagnostic::new(self, page, Span::from_offsets(&page.content, 0, 0), message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_namespace_parent() {
+        let graph = Graph::load("tests/graphs/namespaces").unwrap();
+        let mut diagnostics: Vec<String> = MissingNamespaceParent
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        diagnostics.sort();
+        let path = "tests/graphs/namespaces/pages";
+        assert_eq!(
+            diagnostics,
+            vec![
+                format!(
+                    "{path}/areas___health___sleep.md:1:1: warning[missing-namespace-parent]: \
+                    Namespace \"areas\" has no page"
+                ),
+                format!(
+                    "{path}/areas___health___sleep.md:1:1: warning[missing-namespace-parent]: \
+                    Namespace \"areas/health\" has no page"
+                ),
+                format!(
+                    "{path}/projects___website___design.md:1:1: \
+                    warning[missing-namespace-parent]: \
+                    Namespace \"projects/website\" is titled \"Projects/website\""
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Reports pages that no other page links to.
+
+use crate::graph::{Graph, Page};
+use crate::lint::{Diagnostic, Rule, Severity, Span};
+
+/// Pages that are not linked, tagged or embedded anywhere else in the graph, so they can
+/// only be found by searching or in All Pages. Off by default, it is meant for an
+/// occasional gardening pass rather than every run.
+pub struct OrphanPage;
+
+impl Rule for OrphanPage {
+    fn id(&self) -> &'static str {
+        "orphan-page"
+    }
+
+    fn description(&self) -> &'static str {
+        "Pages should be linked from another page or journal"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check_page(&self, graph: &Graph, page: &Page) -> Vec<Diagnostic> {
+        if graph.config.gardening.excludes(&page.file)
+            || !graph.links.backlinks(&page.file.title).is_empty()
+        {
+            return vec![];
+        }
+        vec![Diagnostic::new(
+            self,
+            page,
+            Span::from_offsets(&page.content, 0, 0),
+            format!("No other page links to \"{}\"", page.file.title),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphan_page() {
+        let mut graph = Graph::load("tests/graphs/links").unwrap();
+        let mut orphans: Vec<String> = OrphanPage
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            orphans,
+            vec![
+                "tests/graphs/links/pages/lonely.md:1:1: info[orphan-page]: \
+                No other page links to \"lonely\"",
+                "tests/graphs/links/pages/paragraph.md:1:1: info[orphan-page]: \
+                No other page links to \"paragraph\"",
+            ]
+        );
+
+        graph.config.gardening.exclude_contents = false;
+        graph.config.gardening.exclude_journals = false;
+        graph
+            .config
+            .gardening
+            .allowlist
+            .insert("lonely".to_string());
+        orphans = OrphanPage
+            .check_graph(&graph)
+            .iter()
+            .map(|d| d.path.clone())
+            .collect();
+        orphans.sort();
+        assert_eq!(
+            orphans,
+            vec![
+                "tests/graphs/links/journals/2024_05_01.md",
+                "tests/graphs/links/pages/contents.md",
+                "tests/graphs/links/pages/paragraph.md",
+            ]
+        );
+    }
+}
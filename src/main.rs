@@ -2,21 +2,303 @@
 // #![warn(missing_docs)]
 // #![warn(clippy::missing_docs_in_private_items)]
 
+mod backend;
+mod config;
+mod graph;
 mod indexer;
+mod lint;
+mod manifest;
 mod meilisearch;
 mod parsing;
-use std::path::PathBuf;
+mod stats;
+use std::path::{Path, PathBuf};
 
-use clap::{command, Parser};
+use backend::memory::MemoryBackend;
+use backend::SearchBackend;
+use clap::{Parser, Subcommand, ValueEnum};
 use dotenv::dotenv;
+use graph::links::Node;
+use graph::{Graph, Page};
 use indexer::Indexer;
+use lint::fix;
+use lint::format::Format;
+use lint::{Registry, Severity};
+use manifest::Manifest;
+use meilisearch::Meilisearch;
+use parsing::block::Block;
+use stats::Stats;
 
-/// Simple program to greet a person
+/// Lint, fix and search a logseq graph
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Input folder path
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Index the graph for searching
+    Index {
+        /// The root of the graph
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Where the index is kept
+        #[arg(long, value_enum, default_value_t = Backend::Meilisearch)]
+        backend: Backend,
+        #[command(flatten)]
+        options: IndexArgs,
+    },
+    /// Lint the graph. Exits with an error if any errors are found.
+    Lint {
+        /// The root of the graph
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        #[command(flatten)]
+        rules: RuleArgs,
+        /// How diagnostics are printed
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// List every rule and whether it is enabled instead of linting
+        #[arg(long)]
+        list_rules: bool,
+    },
+    /// Apply the fixes suggested by lint rules
+    Fix {
+        /// The root of the graph
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        #[command(flatten)]
+        rules: RuleArgs,
+        /// Print a unified diff of the fixes instead of writing them
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Search the indexed blocks, or pages with `--files`
+    Search {
+        query: String,
+        /// The root of the graph, which the local index is kept in
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+        /// Where the index is kept
+        #[arg(long, value_enum, default_value_t = Backend::Meilisearch)]
+        backend: Backend,
+        /// Search pages instead of blocks
+        #[arg(long)]
+        files: bool,
+        /// A Meilisearch filter, e.g. `marker = TODO`
+        #[arg(long)]
+        filter: Option<String>,
+        /// The most results to print
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List the blocks that link to a page, and those that mention it without a link
+    Backlinks {
+        /// The name of the page
+        page: String,
+        /// The root of the graph
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+    },
+    /// Count the pages, blocks, tasks and links in the graph
+    Stats {
+        /// The root of the graph
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
+
+/// Where the search index is kept.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// The Meilisearch server at `MEILISEARCH_URL`
+    Meilisearch,
+    /// A file in the graph's `.logseq-linter` directory, for searching without a server
+    Local,
+}
+
+/// How the graph is indexed.
+#[derive(clap::Args, Debug)]
+struct IndexArgs {
+    /// Index every file, not just the ones that changed since the last run
+    #[arg(long)]
+    full: bool,
+    /// Only index pages, not their blocks
+    #[arg(long)]
+    files_only: bool,
+    /// Stop at the first file that can't be read or parsed, instead of reporting it
+    #[arg(long)]
+    strict: bool,
+    /// The most documents uploaded at once
+    #[arg(long, default_value_t = 1000)]
+    batch_size: usize,
+    /// The most batches uploaded at the same time
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+}
+
+/// Which lint rules run.
+#[derive(clap::Args, Debug)]
+struct RuleArgs {
+    /// Turn on a lint rule by id
+    #[arg(long, value_name = "RULE")]
+    enable: Vec<String>,
+    /// Turn off a lint rule by id
+    #[arg(long, value_name = "RULE")]
+    disable: Vec<String>,
+}
+
+impl RuleArgs {
+    /// The builtin rules, turned on and off as asked.
+    fn registry(&self) -> Result<Registry, String> {
+        let mut registry = Registry::default();
+        for id in self.enable.iter() {
+            registry.enable(id)?;
+        }
+        for id in self.disable.iter() {
+            registry.disable(id)?;
+        }
+        Ok(registry)
+    }
+}
+
+/// Lint the graph at `path`, print every diagnostic, and return whether any were errors.
+fn lint(path: &Path, rules: &RuleArgs, format: Format, list_rules: bool) -> Result<bool, String> {
+    let registry = rules.registry()?;
+    if list_rules {
+        for rule in registry.rules() {
+            let enabled = if registry.is_enabled(rule.id()) {
+                "on"
+            } else {
+                "off"
+            };
+            println!("{} ({}): {}", rule.id(), enabled, rule.description());
+        }
+        return Ok(false);
+    }
+    let graph = Graph::load(path.to_str().unwrap())?;
+    let diagnostics = registry.run(&graph);
+    print!("{}", format.render(&diagnostics, &graph, &registry));
+    Ok(diagnostics.iter().any(|d| d.severity == Severity::Error))
+}
+
+/// Fix what the enabled rules can fix in the graph at `path`, or print the diffs with `diff`.
+fn fix(path: &Path, rules: &RuleArgs, diff: bool) -> Result<(), String> {
+    let registry = rules.registry()?;
+    let graph = Graph::load(path.to_str().unwrap())?;
+    let diagnostics = registry.run(&graph);
+    let fixed_files = fix::fix_graph(&graph, &diagnostics)?;
+    for file in fixed_files.iter() {
+        if diff {
+            print!("{}", file.diff());
+        } else {
+            file.write()?;
+            eprintln!("Fixed {} ({} fixes)", file.path, file.applied);
+        }
+    }
+    let skipped: usize = fixed_files.iter().map(|file| file.skipped).sum();
+    if skipped > 0 {
+        eprintln!(
+            "Skipped {} fixes that overlap other fixes, fix again to apply them",
+            skipped
+        );
+    }
+    Ok(())
+}
+
+/// Index the files under `path` that changed since the last run, then print what was done.
+async fn index<B: SearchBackend>(
+    db: B,
+    manifest_path: PathBuf,
+    path: &Path,
+    options: &IndexArgs,
+) -> Result<(), String> {
+    if options.full && manifest_path.exists() {
+        std::fs::remove_file(&manifest_path).map_err(|e| e.to_string())?;
+    }
+    let report = Indexer::new(db)
+        .with_manifest(manifest_path)
+        .with_strict(options.strict)
+        .with_batch_size(options.batch_size)
+        .with_concurrency(options.concurrency)
+        .index_files(path.to_str().unwrap(), !options.files_only)
+        .await?;
+    eprint!("{}", report);
+    Ok(())
+}
+
+/// Print the best matches for `query`, each with the place it can be opened at.
+async fn search<B: SearchBackend>(
+    db: B,
+    query: &str,
+    files: bool,
+    filter: Option<&str>,
+    limit: usize,
+) -> Result<(), String> {
+    if files {
+        for file in db.search_files(query, filter, limit).await? {
+            println!("{}: {}", file.path, file.title);
+        }
+        return Ok(());
+    }
+    for block in db.search_blocks(query, filter, limit).await? {
+        let path = match db.get_file(&block.file_id).await? {
+            Some(file) => file.path,
+            None => block.file_id.clone(),
+        };
+        println!(
+            "{}:{}:{}: {}",
+            path,
+            block.span.start.line,
+            block.span.start.column,
+            block.content.lines().next().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+/// Print where the page named `name` is linked and mentioned.
+fn backlinks(path: &Path, name: &str) -> Result<(), String> {
+    let graph = Graph::load(path.to_str().unwrap())?;
+    let linked = graph.links.backlinks(name);
+    if linked.is_empty() && !graph.links.is_page(name) {
+        return Err(format!("No page is named or linked as \"{}\"", name));
+    }
+    let print = |page: &Page, block: Option<&Block>| {
+        let (location, content) = match block {
+            Some(block) => (block.span.start, block.content.as_str()),
+            None => (Default::default(), page.content.as_str()),
+        };
+        println!(
+            "{}:{}:{}: {}",
+            page.file.path,
+            location.line.max(1),
+            location.column.max(1),
+            content.lines().next().unwrap_or("")
+        );
+    };
+    println!("Linked references:");
+    for edge in linked {
+        match &edge.source {
+            Node::Block(id) => {
+                if let Some((page, block)) = graph.block(id) {
+                    print(page, Some(block));
+                }
+            }
+            Node::Page(_) => {
+                let key = graph.links.page_of(&edge.source).unwrap_or_default();
+                if let Some(page) = graph.resolve(key) {
+                    print(page, None);
+                }
+            }
+        }
+    }
+    println!("Unlinked references:");
+    for (page, block) in graph.unlinked_references(name) {
+        print(page, Some(block));
+    }
+    Ok(())
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -24,12 +306,64 @@ async fn main() {
     // Load environment variables from a .env file
     dotenv().ok();
 
-    // Load the files into the database
-    let args = Args::parse();
-
-    Indexer::new()
-        .await
-        .index_files(args.path.to_str().unwrap(), true)
-        .await
-        .unwrap();
+    let result = match Args::parse().command {
+        Command::Index {
+            path,
+            backend: Backend::Meilisearch,
+            options,
+        } => match Meilisearch::new().await {
+            Ok(db) => index(db, Manifest::default_path(&path), &path, &options).await,
+            Err(msg) => Err(msg),
+        },
+        Command::Index {
+            path,
+            backend: Backend::Local,
+            options,
+        } => {
+            let index_path = MemoryBackend::default_path(&path);
+            let manifest_path = index_path.with_file_name("manifest.json");
+            match MemoryBackend::open(&index_path) {
+                Ok(db) => index(db, manifest_path, &path, &options).await,
+                Err(msg) => Err(msg),
+            }
+        }
+        Command::Lint {
+            path,
+            rules,
+            format,
+            list_rules,
+        } => match lint(&path, &rules, format, list_rules) {
+            Ok(true) => std::process::exit(1),
+            result => result.map(|_| ()),
+        },
+        Command::Fix { path, rules, diff } => fix(&path, &rules, diff),
+        Command::Search {
+            query,
+            path,
+            backend,
+            files,
+            filter,
+            limit,
+        } => {
+            let filter = filter.as_deref();
+            match backend {
+                Backend::Meilisearch => match Meilisearch::new().await {
+                    Ok(db) => search(db, &query, files, filter, limit).await,
+                    Err(msg) => Err(msg),
+                },
+                Backend::Local => match MemoryBackend::open(&MemoryBackend::default_path(&path)) {
+                    Ok(db) => search(db, &query, files, filter, limit).await,
+                    Err(msg) => Err(msg),
+                },
+            }
+        }
+        Command::Backlinks { page, path } => backlinks(&path, &page),
+        Command::Stats { path } => Graph::load(path.to_str().unwrap()).map(|graph| {
+            print!("{}", Stats::new(&graph));
+        }),
+    };
+    if let Err(msg) = result {
+        eprintln!("{}", msg);
+        std::process::exit(2);
+    }
 }
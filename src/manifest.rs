@@ -0,0 +1,173 @@
+//! A record of what was indexed last time, so unchanged files can be skipped.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// What the manifest knows about one indexed file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Entry {
+    /// The modification time of the file in nanoseconds since the unix epoch
+    pub mtime: u64,
+    /// The sha256 of the file content
+    pub hash: String,
+    /// The id of the file's document
+    pub file_id: String,
+    /// Whether the file's blocks were indexed too
+    pub blocks: bool,
+    /// The title of the page, for linking it to other pages without reading it again
+    #[serde(default)]
+    pub title: String,
+    /// The other names of the page, from its `alias::` property
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The pages the file links to
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// The pages the file is tagged with
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The pages that link to the file, as last indexed
+    #[serde(default)]
+    pub backlinks: Vec<String>,
+    /// The number of references to the file, as last indexed
+    #[serde(default)]
+    pub backlink_count: usize,
+}
+
+/// Indexed files by their path relative to the graph root.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Manifest {
+    pub files: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    /// Where the manifest for the graph at `root` is stored.
+    pub fn default_path(root: &Path) -> PathBuf {
+        root.join(".logseq-linter").join("manifest.json")
+    }
+
+    /// Load a manifest, or an empty one if it has never been saved.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    /// The sha256 of some content, as hex.
+    pub fn hash(content: &str) -> String {
+        Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// The modification time of a file in nanoseconds since the unix epoch.
+    pub fn mtime(path: &Path) -> Result<u64, String> {
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| e.to_string())?;
+        Ok(modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0))
+    }
+
+    /// The entry for `relative`, if it was indexed with at least as much as `blocks` asks for.
+    fn indexed(&self, relative: &str, blocks: bool) -> Option<&Entry> {
+        self.files
+            .get(relative)
+            .filter(|entry| entry.blocks || !blocks)
+    }
+
+    /// Whether the file at `relative` has not been touched since it was indexed.
+    /// This only compares modification times, so it is cheap enough to call before reading the file.
+    pub fn is_untouched(&self, relative: &str, mtime: u64, blocks: bool) -> bool {
+        self.indexed(relative, blocks)
+            .is_some_and(|entry| entry.mtime == mtime)
+    }
+
+    /// Whether the content at `relative` is the same as when it was indexed.
+    pub fn is_unchanged(&self, relative: &str, hash: &str, blocks: bool) -> bool {
+        self.indexed(relative, blocks)
+            .is_some_and(|entry| entry.hash == hash)
+    }
+
+    /// Entries that are not in `current`, i.e. files that were deleted or renamed since.
+    pub fn removed<'a>(
+        &'a self,
+        current: &'a Manifest,
+    ) -> impl Iterator<Item = (&'a String, &'a Entry)> {
+        self.files
+            .iter()
+            .filter(move |(path, _)| !current.files.contains_key(*path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: &str) -> Entry {
+        Entry {
+            mtime: 1,
+            hash: hash.to_string(),
+            file_id: "id".to_string(),
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(
+            Manifest::hash("foo"),
+            "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let manifest = Manifest {
+            files: BTreeMap::from([
+                ("pages/a.md".to_string(), entry("a")),
+                ("pages/b.md".to_string(), entry("b")),
+            ]),
+        };
+        assert!(manifest.is_untouched("pages/a.md", 1, false));
+        assert!(!manifest.is_untouched("pages/a.md", 2, false));
+        assert!(manifest.is_unchanged("pages/a.md", "a", false));
+        assert!(!manifest.is_unchanged("pages/a.md", "b", false));
+        assert!(!manifest.is_unchanged("pages/c.md", "a", false));
+        assert!(
+            !manifest.is_unchanged("pages/a.md", "a", true),
+            "Files indexed without their blocks need indexing again to add them"
+        );
+        let current = Manifest {
+            files: BTreeMap::from([
+                ("pages/a.md".to_string(), entry("a")),
+                ("pages/c.md".to_string(), entry("c")),
+            ]),
+        };
+        let removed: Vec<&String> = manifest.removed(&current).map(|(path, _)| path).collect();
+        assert_eq!(removed, vec!["pages/b.md"]);
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let manifest = Manifest::load(Path::new("graph/no-such-manifest.json")).unwrap();
+        assert_eq!(manifest, Manifest::default());
+    }
+}
@@ -1,20 +1,254 @@
 //! Meilisearch is a powerful, fast, open-source, easy to use text search engine.
+use meilisearch_sdk::documents::DocumentDeletionQuery;
+use meilisearch_sdk::errors::{Error, ErrorCode};
+use meilisearch_sdk::settings::Settings;
+use meilisearch_sdk::task_info::TaskInfo;
 use meilisearch_sdk::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::env;
 
+use crate::backend::SearchBackend;
+use crate::parsing::block::Block;
+use crate::parsing::file::File;
+
+/// Bump this whenever the index settings or the shape of the documents change. Indexes
+/// set up with another version are cleared, so the graph is indexed again from scratch.
+pub const SCHEMA_VERSION: u32 = 7;
+
+/// Where the schema version the indexes were set up with is kept.
+const META_INDEX: &str = "meta";
+
+#[derive(Serialize, Deserialize)]
+struct Schema {
+    id: String,
+    version: u32,
+}
+
 pub struct Meilisearch {
     pub client: Client,
 }
 
 impl Meilisearch {
-    pub async fn new() -> Meilisearch {
+    /// Connect to the server at `MEILISEARCH_URL` with `MEILISEARCH_API_KEY`.
+    pub async fn new() -> Result<Meilisearch, String> {
         let url =
             env::var("MEILISEARCH_URL").unwrap_or_else(|_| "http://localhost:7700".to_string());
         let api_key = env::var("MEILISEARCH_API_KEY").unwrap_or_else(|_| "masterKey".to_string());
+        Self::connect(&url, &api_key).await
+    }
+
+    /// Check the server is up and accepts the key, then make sure the indexes exist.
+    /// Existing indexes and their documents are reused.
+    pub async fn connect(url: &str, api_key: &str) -> Result<Meilisearch, String> {
         let client = Client::new(url, Some(api_key));
-        client.create_index("files", Some("id")).await.unwrap();
-        client.create_index("blocks", Some("id")).await.unwrap();
-        Meilisearch { client }
+        client.health().await.map_err(|e| {
+            format!(
+                "Could not reach Meilisearch at {}: {} Is it running? `docker-compose up -d` starts one.",
+                url, e
+            )
+        })?;
+        // The health check works without a key, so check the key separately
+        client.get_version().await.map_err(|e| match e {
+            Error::Meilisearch(e)
+                if matches!(
+                    e.error_code,
+                    ErrorCode::InvalidApiKey | ErrorCode::MissingAuthorizationHeader
+                ) =>
+            {
+                format!(
+                    "Meilisearch at {} rejected the API key, set MEILISEARCH_API_KEY",
+                    url
+                )
+            }
+            e => e.to_string(),
+        })?;
+        let db = Meilisearch { client };
+        for uid in ["files", "blocks", META_INDEX] {
+            db.ensure_index(uid).await?;
+        }
+        Ok(db)
+    }
+
+    /// Wait for a task and turn a failed task into an error.
+    pub async fn wait(&self, task: Result<TaskInfo, Error>) -> Result<(), String> {
+        let task = task
+            .map_err(|e| e.to_string())?
+            .wait_for_completion(&self.client, None, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        if task.is_failure() {
+            return Err(task.unwrap_failure().to_string());
+        }
+        Ok(())
+    }
+
+    /// Create an index unless it exists already.
+    async fn ensure_index(&self, uid: &str) -> Result<(), String> {
+        match self.client.get_index(uid).await {
+            Ok(_) => Ok(()),
+            Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::IndexNotFound => {
+                self.wait(self.client.create_index(uid, Some("id")).await)
+                    .await
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// The settings of the `files` and `blocks` indexes for `SCHEMA_VERSION`.
+    pub fn settings(uid: &str) -> Settings {
+        match uid {
+            "files" => Settings::new()
+                .with_searchable_attributes(["title", "aliases", "properties", "tags", "path"])
+                .with_filterable_attributes([
+                    "path",
+                    "title",
+                    "normalized_title",
+                    "aliases",
+                    "namespace_parent",
+                    "namespace_path",
+                    "tags",
+                    "wikilinks",
+                    "normalized_tags",
+                    "normalized_wikilinks",
+                    "block_refs",
+                    "properties",
+                    "is_journal",
+                    "journal_day",
+                    "backlinks",
+                    "backlink_count",
+                ])
+                .with_sortable_attributes(["title", "journal_day", "backlink_count"]),
+            // Blocks are deleted by file when a file is re-indexed or removed
+            _ => Settings::new()
+                .with_searchable_attributes(["content", "properties", "tags", "wikilinks"])
+                .with_filterable_attributes([
+                    "file_id",
+                    "parent_block_id",
+                    "tags",
+                    "wikilinks",
+                    "normalized_tags",
+                    "normalized_wikilinks",
+                    "block_refs",
+                    "properties",
+                    "marker",
+                    "priority",
+                    "scheduled",
+                    "deadline",
+                ])
+                .with_sortable_attributes(["priority", "scheduled", "deadline"]),
+        }
+    }
+
+    /// The schema version the indexes were set up with, if they were set up at all.
+    async fn schema_version(&self) -> Result<Option<u32>, String> {
+        let schema = self.get::<Schema>(META_INDEX, "schema").await?;
+        Ok(schema.map(|schema| schema.version))
+    }
+
+    /// Search the `files` or `blocks` index, optionally narrowed down by a filter such as
+    /// `marker = TODO`.
+    async fn search<T: 'static + DeserializeOwned>(
+        &self,
+        index: &str,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<T>, String> {
+        let index = self.client.index(index);
+        let mut search = index.search();
+        search.with_query(query).with_limit(limit);
+        if let Some(filter) = filter {
+            search.with_filter(filter);
+        }
+        let results = search.execute::<T>().await.map_err(|e| e.to_string())?;
+        Ok(results.hits.into_iter().map(|hit| hit.result).collect())
+    }
+
+    /// Get a document by id from the `files` or `blocks` index.
+    async fn get<T: 'static + DeserializeOwned>(
+        &self,
+        index: &str,
+        id: &str,
+    ) -> Result<Option<T>, String> {
+        match self.client.index(index).get_document::<T>(id).await {
+            Ok(document) => Ok(Some(document)),
+            Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::DocumentNotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl SearchBackend for Meilisearch {
+    /// Set the indexes up for `SCHEMA_VERSION` if they were set up for another version.
+    /// Their documents were indexed for the old schema, so they are removed and `true` is
+    /// returned to tell the caller to index everything again.
+    async fn migrate(&self) -> Result<bool, String> {
+        if self.schema_version().await? == Some(SCHEMA_VERSION) {
+            return Ok(false);
+        }
+        for uid in ["files", "blocks"] {
+            let index = self.client.index(uid);
+            self.wait(index.delete_all_documents().await).await?;
+            self.wait(index.set_settings(&Self::settings(uid)).await)
+                .await?;
+        }
+        let schema = Schema {
+            id: "schema".to_string(),
+            version: SCHEMA_VERSION,
+        };
+        let meta = self.client.index(META_INDEX);
+        self.wait(meta.add_or_replace(&[schema], Some("id")).await)
+            .await?;
+        Ok(true)
+    }
+
+    async fn upsert_files(&self, files: &[File]) -> Result<(), String> {
+        let index = self.client.index("files");
+        self.wait(index.add_documents(files, Some("id")).await)
+            .await
+    }
+
+    async fn upsert_blocks(&self, blocks: &[Block]) -> Result<(), String> {
+        let index = self.client.index("blocks");
+        self.wait(index.add_documents(blocks, Some("id")).await)
+            .await
+    }
+
+    async fn delete_files(&self, file_ids: &[String]) -> Result<(), String> {
+        let index = self.client.index("files");
+        self.wait(index.delete_documents(file_ids).await).await
+    }
+
+    async fn delete_blocks(&self, file_ids: &[String]) -> Result<(), String> {
+        let index = self.client.index("blocks");
+        let ids: Vec<String> = file_ids.iter().map(|id| format!("\"{}\"", id)).collect();
+        let filter = format!("file_id IN [{}]", ids.join(", "));
+        let mut query = DocumentDeletionQuery::new(&index);
+        query.with_filter(&filter);
+        self.wait(index.delete_documents_with(&query).await).await
+    }
+
+    async fn search_files(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<File>, String> {
+        self.search("files", query, filter, limit).await
+    }
+
+    async fn search_blocks(
+        &self,
+        query: &str,
+        filter: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Block>, String> {
+        self.search("blocks", query, filter, limit).await
+    }
+
+    async fn get_file(&self, id: &str) -> Result<Option<File>, String> {
+        self.get("files", id).await
     }
 }
 
@@ -33,12 +267,35 @@ mod tests {
         genres: Vec<String>,
     }
 
+    #[tokio::test]
+    async fn test_unreachable_server() {
+        let error = Meilisearch::connect("http://127.0.0.1:1", "masterKey")
+            .await
+            .err()
+            .unwrap();
+        assert!(error.starts_with("Could not reach Meilisearch at http://127.0.0.1:1"));
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = Meilisearch::settings("blocks");
+        let filterable = settings.filterable_attributes.unwrap();
+        assert!(filterable.contains(&"file_id".to_string()));
+        assert!(filterable.contains(&"parent_block_id".to_string()));
+        assert!(settings.sortable_attributes.is_some());
+        let settings = Meilisearch::settings("files");
+        assert!(settings
+            .filterable_attributes
+            .unwrap()
+            .contains(&"tags".to_string()));
+    }
+
     #[tokio::test]
     async fn test_add_documents_and_search() {
         dotenv().ok();
 
         // Create a client (without sending any request so that can't fail)
-        let client = Meilisearch::new().await.client;
+        let client = Meilisearch::new().await.unwrap().client;
 
         // An index is where the documents are stored.
         client.create_index("movies", Some("id")).await.unwrap();
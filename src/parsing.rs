@@ -1,2 +1,14 @@
 pub mod block;
 pub mod file;
+pub mod journal;
+pub mod markup;
+pub mod name;
+pub mod property;
+pub mod reference;
+pub mod span;
+pub mod task;
+
+use uuid::Uuid;
+
+/// The namespace that file and block ids are derived in, so the same file always gets the same id.
+pub const ID_NAMESPACE: Uuid = Uuid::from_u128(0xf1e6eaf1_7c41_4a8e_b39f_e61db0cf5a87);
@@ -1,16 +1,36 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
+use chrono::NaiveDate;
 use markdown::{
     mdast::{ListItem, Node},
     unist::Position,
 };
-use regex::Regex;
+use regex::{Match, Regex};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::parsing::journal;
+use crate::parsing::markup;
+use crate::parsing::name::NameNormalizer;
+use crate::parsing::property::{parse_properties, PropertyValue};
+use crate::parsing::reference::{references, Reference};
+use crate::parsing::span::Span;
+use crate::parsing::task::{self, Marker, Priority};
+use crate::parsing::ID_NAMESPACE;
 
 pub struct BlockBuilder {
     file_id: Option<String>,
     file_path: Option<PathBuf>,
     parent_block_id: Option<String>,
+    /// The index of the block among its siblings, for it and each of its ancestors
+    position: Vec<usize>,
+    /// How the names of linked pages are normalized
+    normalizer: NameNormalizer,
+    /// Property keys whose values are comma separated lists
+    separated_by_commas: HashSet<String>,
 }
 
 impl BlockBuilder {
@@ -19,6 +39,9 @@ impl BlockBuilder {
             file_id: None,
             file_path: None,
             parent_block_id: None,
+            position: vec![],
+            normalizer: NameNormalizer::default(),
+            separated_by_commas: HashSet::new(),
         }
     }
 
@@ -37,7 +60,28 @@ impl BlockBuilder {
         self
     }
 
-    fn get_slice(&self, content: &str, list_item: &ListItem) -> Result<String, String> {
+    /// How the names of linked pages are normalized, see `GraphConfig::normalizer`.
+    pub fn with_normalizer(mut self, normalizer: NameNormalizer) -> BlockBuilder {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Property keys whose values are comma separated lists, see
+    /// `GraphConfig::property_separated_by_commas`.
+    pub fn with_separated_by_commas(mut self, keys: HashSet<String>) -> BlockBuilder {
+        self.separated_by_commas = keys;
+        self
+    }
+
+    /// Where the block sits in the file's tree of blocks, e.g. `[0, 2]` is the
+    /// third child of the first top level block.
+    pub fn with_position(mut self, position: Vec<usize>) -> BlockBuilder {
+        self.position = position;
+        self
+    }
+
+    /// The byte range of the block's own text, without its children or surrounding whitespace.
+    fn get_range(content: &str, list_item: &ListItem) -> (usize, usize) {
         let position = list_item.position.as_ref().unwrap();
         let first_list_item_position: Option<Position> = list_item
             .children
@@ -47,140 +91,233 @@ impl BlockBuilder {
                 _ => None,
             })
             .next();
-        if let Some(first_list_item_position) = first_list_item_position {
-            Ok(
-                content[position.start.offset..first_list_item_position.start.offset]
-                    .trim()
-                    .to_string(),
-            )
-        } else {
-            Ok(content[position.start.offset..position.end.offset]
-                .trim()
-                .to_string())
-        }
+        let end = first_list_item_position
+            .map(|p| p.start.offset)
+            .unwrap_or(position.end.offset);
+        let start = position.start.offset;
+        let slice = &content[start..end];
+        let leading = slice.len() - slice.trim_start().len();
+        (start + leading, start + slice.trim_end().len())
     }
 
-    fn get_id(content: &str) -> String {
-        let re = Regex::new("id:: ([a-f0-9-]+)");
-        if let Some(captures) = re.unwrap().captures(content) {
-            match captures.get(1) {
-                Some(id) => id.as_str().to_string(),
-                None => uuid::Uuid::new_v4().to_string(),
-            }
-        } else {
-            uuid::Uuid::new_v4().to_string()
-        }
+    fn get_slice(&self, content: &str, list_item: &ListItem) -> Result<String, String> {
+        let (start, end) = Self::get_range(content, list_item);
+        Ok(content[start..end].to_string())
+    }
+
+    /// The uuid set by an `id::` property line, if the block has one.
+    pub fn get_id(content: &str) -> Option<Match<'_>> {
+        let re = Regex::new(r"(?m)^\s*(?:- )?id:: ([0-9a-f-]{36})\s*$").unwrap();
+        re.captures(content).and_then(|captures| captures.get(1))
     }
 
-    fn get_properties(content: &str) -> HashMap<String, String> {
-        let re = Regex::new(r"([a-z]+):: ([a-z]+)").unwrap();
-        let mut properties = HashMap::new();
-        for captures in re.captures_iter(content) {
-            assert_eq!(
-                captures.len(),
-                3,
-                "There should be the full capture, a key, and a value: {:?}",
-                captures
-            );
-            let k = captures[1].to_string();
-            let v = captures[2].to_string();
-            if k != "id" {
-                properties.insert(k, v);
+    /// Derive an id from the file and the block's position in it, so blocks
+    /// without an `id::` keep their id between runs.
+    fn get_derived_id(file_id: &str, position: &[usize]) -> String {
+        let position: Vec<String> = position.iter().map(|i| i.to_string()).collect();
+        let name = format!("{}/{}", file_id, position.join("."));
+        Uuid::new_v5(&ID_NAMESPACE, name.as_bytes()).to_string()
+    }
+
+    /// The lines of `slice` that start in its `prose`, so `key:: value` lines in code are not
+    /// properties. Whole lines are kept, as values may have URLs or code in them.
+    fn get_prose_lines(slice: &str, prose: &str) -> String {
+        let mut offset = 0;
+        let mut lines = String::new();
+        for line in slice.split_inclusive('\n') {
+            let first = offset + line.len() - line.trim_start().len();
+            if prose.as_bytes().get(first) == slice.as_bytes().get(first) {
+                lines.push_str(line);
             }
+            offset += line.len();
         }
-        properties
+        lines
+    }
+
+    fn get_properties(
+        content: &str,
+        separated_by_commas: &HashSet<String>,
+    ) -> HashMap<String, PropertyValue> {
+        parse_properties(content, separated_by_commas)
+            .into_iter()
+            .filter(|(k, _)| k != "id")
+            .collect()
     }
 
     fn get_wikilinks(content: &str) -> Vec<String> {
         // [[something]] but not #[[something]]
-        let re = Regex::new(r"\s\[\[([\w\s]+)\]\]").unwrap();
-        let mut wikilinks = vec![];
-        for captures in re.captures_iter(content) {
-            assert_eq!(
-                captures.len(),
-                2,
-                "There should be the full capture and the wikilink: {:?}",
-                captures
-            );
-            wikilinks.push(captures[1].trim().to_string());
-        }
-        wikilinks
+        references(content)
+            .into_iter()
+            .filter(|reference| !reference.is_tag())
+            .map(|reference| reference.name)
+            .collect()
     }
 
     fn get_tags(content: &str) -> Vec<String> {
-        // #something or #[[something]]
-        let re = Regex::new(r"(?i)#\[\[([\w\s]+)\]\]|#(\w+)").unwrap();
-        let mut tags = vec![];
-        for captures in re.captures_iter(content) {
-            assert_eq!(
-                captures.len(),
-                3,
-                "There should be the full capture and the tag: {:?}",
-                captures
-            );
-            if let Some(tag) = captures.get(1) {
-                tags.push(tag.as_str().to_string());
-            } else if let Some(tag) = captures.get(2) {
-                tags.push(tag.as_str().to_string());
-            } else {
-                panic!("No tag found");
-            }
-        }
-        tags
+        references(content)
+            .into_iter()
+            .filter(Reference::is_tag)
+            .map(|reference| reference.name)
+            .collect()
+    }
+
+    /// The uuids of block references, `((uuid))`, including those in `{{embed ((uuid))}}`.
+    pub fn get_block_refs(content: &str) -> Vec<String> {
+        let re =
+            Regex::new(r"\(\(([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})\)\)")
+                .unwrap();
+        re.captures_iter(content)
+            .map(|captures| captures[1].to_string())
+            .collect()
     }
 
     pub fn build(self, content: &str, list_item: &ListItem) -> Result<Vec<Block>, String> {
         let slice = self.get_slice(content, list_item)?;
-        let id = Self::get_id(&slice);
-        let properties = Self::get_properties(&slice);
-        let wikilinks = Self::get_wikilinks(&slice);
-        let tags = Self::get_tags(&slice);
+        let (start, end) = Self::get_range(content, list_item);
+        let span = Span::from_offsets(content, start, end);
         let file_id = self.file_id.expect("No file id");
+        let prose = markup::prose(content, start, end, &list_item.children);
+        let properties = Self::get_properties(
+            &Self::get_prose_lines(&slice, &prose),
+            &self.separated_by_commas,
+        );
+        // Not from the slice, so an `id::` in a code block is not the block's id
+        let id_match = Self::get_id(&prose);
+        let id_span =
+            id_match.map(|id| Span::from_offsets(content, start + id.start(), start + id.end()));
+        let id = id_match
+            .map(|id| id.as_str().to_string())
+            .unwrap_or_else(|| Self::get_derived_id(&file_id, &self.position));
+        let wikilinks = Self::get_wikilinks(&prose);
+        let tags = Self::get_tags(&prose);
+        let block_refs = Self::get_block_refs(&prose);
+        let marker = Marker::parse(&slice);
+        let priority = Priority::parse(&slice);
+        let scheduled = task::parse_timestamp(&slice, "SCHEDULED");
+        let deadline = task::parse_timestamp(&slice, "DEADLINE");
         let mut blocks = vec![];
-        for child in list_item.children.iter() {
-            if let Node::List(list) = child {
-                for child in list.children.iter() {
-                    if let Node::ListItem(list_item) = child {
-                        let block = BlockBuilder::new()
-                            .with_file_id(file_id.clone())
-                            .with_parent_block_id(id.clone())
-                            .build(content, list_item)?;
-                        blocks.extend(block);
-                    }
-                }
-            }
+        let children = list_item
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Node::List(list) => Some(list.children.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|child| match child {
+                Node::ListItem(list_item) => Some(list_item),
+                _ => None,
+            });
+        for (i, list_item) in children.enumerate() {
+            let mut position = self.position.clone();
+            position.push(i);
+            let block = BlockBuilder::new()
+                .with_file_id(file_id.clone())
+                .with_parent_block_id(id.clone())
+                .with_position(position)
+                .with_normalizer(self.normalizer)
+                .with_separated_by_commas(self.separated_by_commas.clone())
+                .build(content, list_item)?;
+            blocks.extend(block);
         }
         let root = Block {
             id,
+            id_span,
             content: slice,
+            span,
             file_id,
             properties,
+            normalized_wikilinks: self.normalizer.normalize_all(&wikilinks),
+            normalized_tags: self.normalizer.normalize_all(&tags),
             wikilinks,
             tags,
+            block_refs,
+            marker,
+            priority,
+            scheduled,
+            deadline,
             parent_block_id: self.parent_block_id,
         };
         blocks.push(root);
         Ok(blocks)
     }
+
+    /// Build every block in a file from the top level list items of its ast.
+    pub fn build_all(self, content: &str, ast: &Node) -> Result<Vec<Block>, String> {
+        let file_id = self.file_id.expect("No file id");
+        let mut blocks = vec![];
+        let no_children = vec![];
+        let list_items = ast
+            .children()
+            .unwrap_or(&no_children)
+            .iter()
+            .flat_map(|child| match child {
+                Node::List(list) => list.children.iter().collect(),
+                Node::ListItem(_) => vec![child],
+                _ => vec![],
+            })
+            .filter_map(|child| match child {
+                Node::ListItem(list_item) => Some(list_item),
+                _ => None,
+            })
+            .collect::<Vec<&ListItem>>();
+        for (i, list_item) in list_items.into_iter().enumerate() {
+            let mut builder = BlockBuilder::new()
+                .with_file_id(file_id.clone())
+                .with_position(vec![i])
+                .with_normalizer(self.normalizer)
+                .with_separated_by_commas(self.separated_by_commas.clone());
+            if let Some(file_path) = self.file_path.clone() {
+                builder = builder.with_file_path(file_path);
+            }
+            blocks.extend(builder.build(content, list_item)?);
+        }
+        Ok(blocks)
+    }
 }
 
 /// This is a logseq block, which is a markdown list element
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Block {
     /// The index of the block in the list
     pub id: String,
+    /// Where the uuid of the block's `id::` property is in the file, if it sets its own id
+    #[serde(default)]
+    pub id_span: Option<Span>,
     /// The text content of the block divided into types
     pub content: String,
+    /// Where the block's own text is in the file, without its children
+    #[serde(default)]
+    pub span: Span,
     /// The file this block belongs to
     pub file_id: String,
     /// Parent block id
     pub parent_block_id: Option<String>,
     /// The block properties
-    pub properties: HashMap<String, String>,
+    pub properties: HashMap<String, PropertyValue>,
     /// The block tags
     pub tags: Vec<String>,
     /// The wikilinks in the block
     pub wikilinks: Vec<String>,
+    /// `wikilinks`, normalized so the same page is always written the same way
+    #[serde(default)]
+    pub normalized_wikilinks: Vec<String>,
+    /// `tags`, normalized so the same page is always written the same way
+    #[serde(default)]
+    pub normalized_tags: Vec<String>,
+    /// The ids of blocks referenced or embedded in the block
+    #[serde(default)]
+    pub block_refs: Vec<String>,
+    /// The task marker, e.g. `TODO`
+    pub marker: Option<Marker>,
+    /// The task priority, e.g. `[#A]`
+    pub priority: Option<Priority>,
+    /// The `SCHEDULED:` date, stored as a `yyyymmdd` number
+    #[serde(default, with = "journal::day")]
+    pub scheduled: Option<NaiveDate>,
+    /// The `DEADLINE:` date, stored as a `yyyymmdd` number
+    #[serde(default, with = "journal::day")]
+    pub deadline: Option<NaiveDate>,
 }
 
 #[cfg(test)]
@@ -263,15 +400,88 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn test_spans() {
+                let content =
+                    std::fs::read_to_string("graph/pages/tests___parsing___blocks___hierarchy.md")
+                        .unwrap();
+                let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+                let blocks = BlockBuilder::new()
+                    .with_file_id("file".to_string())
+                    .build_all(&content, &ast)
+                    .unwrap();
+                let spans: Vec<(&str, usize, usize, usize, usize)> = blocks
+                    .iter()
+                    .map(|b| {
+                        (
+                            b.content.as_str(),
+                            b.span.start.line,
+                            b.span.start.column,
+                            b.span.end.line,
+                            b.span.end.column,
+                        )
+                    })
+                    .collect();
+                assert_eq!(
+                    spans,
+                    vec![
+                        ("- Ipsum", 5, 3, 5, 10),
+                        ("- Sit", 7, 5, 7, 10),
+                        ("- Dolor", 6, 3, 6, 10),
+                        ("- Lorem", 4, 1, 4, 8),
+                        ("- Amet", 8, 1, 8, 7),
+                    ]
+                );
+                for block in blocks.iter() {
+                    assert_eq!(
+                        &content[block.span.start.offset..block.span.end.offset],
+                        block.content
+                    );
+                }
+            }
+
             #[test]
             fn test_get_id() {
                 let list_items = get_list_blocks_as_str();
 
                 // The first and third items have an id
                 let first = BlockBuilder::get_id(&list_items[0]);
-                assert_eq!(first, "662ef9e2-4b89-4f7d-9a54-afd395b03cb0");
+                assert_eq!(
+                    first.unwrap().as_str(),
+                    "662ef9e2-4b89-4f7d-9a54-afd395b03cb0"
+                );
+                let second = BlockBuilder::get_id(&list_items[1]);
+                assert_eq!(second, None);
                 let third = BlockBuilder::get_id(&list_items[2]);
-                assert_eq!(third, "662effa7-a861-42df-a5bf-64c783eb8b64");
+                assert_eq!(
+                    third.unwrap().as_str(),
+                    "662effa7-a861-42df-a5bf-64c783eb8b64"
+                );
+                // Only a whole property line sets the id
+                let inline = "- see id:: 662ef9e2-4b89-4f7d-9a54-afd395b03cb0 for more";
+                assert_eq!(BlockBuilder::get_id(inline), None);
+            }
+
+            #[test]
+            fn test_derived_ids_are_stable() {
+                let content =
+                    std::fs::read_to_string("graph/pages/tests___parsing___blocks___property.md")
+                        .unwrap();
+                let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+                let build = || {
+                    BlockBuilder::new()
+                        .with_file_id("file".to_string())
+                        .build_all(&content, &ast)
+                        .unwrap()
+                        .into_iter()
+                        .map(|block| block.id)
+                        .collect::<Vec<String>>()
+                };
+                let ids = build();
+                assert_eq!(ids, build());
+                assert_eq!(ids[0], "662ef9e2-4b89-4f7d-9a54-afd395b03cb0");
+                assert_eq!(ids[1], BlockBuilder::get_derived_id("file", &[1]));
+                assert_ne!(ids[1], ids[3]);
             }
 
             #[test]
@@ -279,22 +489,93 @@ mod tests {
                 let list_items = get_list_blocks_as_str();
 
                 // The first and second items have properties foo:: bar
-                let first = BlockBuilder::get_properties(&list_items[0]);
-                assert_eq!(first.get("foo"), Some(&"bar".to_string()));
-                let second = BlockBuilder::get_properties(&list_items[1]);
-                assert_eq!(second.get("foo"), Some(&"bar".to_string()));
-                let third = BlockBuilder::get_properties(&list_items[2]);
+                let bar = PropertyValue::Text("bar".to_string());
+                let first = BlockBuilder::get_properties(&list_items[0], &HashSet::new());
+                assert_eq!(first.get("foo"), Some(&bar));
+                let second = BlockBuilder::get_properties(&list_items[1], &HashSet::new());
+                assert_eq!(second.get("foo"), Some(&bar));
+                let third = BlockBuilder::get_properties(&list_items[2], &HashSet::new());
                 assert_eq!(third.len(), 0);
-                let fourth = BlockBuilder::get_properties(&list_items[3]);
+                let fourth = BlockBuilder::get_properties(&list_items[3], &HashSet::new());
                 assert_eq!(fourth.len(), 0);
             }
 
+            #[test]
+            fn test_get_typed_properties() {
+                let content = std::fs::read_to_string(
+                    "graph/pages/tests___parsing___blocks___property_types.md",
+                )
+                .unwrap();
+                let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+                let blocks = BlockBuilder::new()
+                    .with_file_id("file".to_string())
+                    .build_all(&content, &ast)
+                    .unwrap();
+                let properties = &blocks[0].properties;
+                let date = chrono::NaiveDate::from_ymd_opt(2024, 4, 28).unwrap();
+                let page = |name: &str| PropertyValue::PageRef {
+                    page: name.to_string(),
+                };
+                assert_eq!(properties.get("due-date"), Some(&PropertyValue::Date(date)));
+                assert_eq!(
+                    properties.get("created_at"),
+                    Some(&PropertyValue::Date(date))
+                );
+                assert_eq!(properties.get("count2"), Some(&PropertyValue::Number(3.0)));
+                assert_eq!(properties.get("done"), Some(&PropertyValue::Bool(true)));
+                assert_eq!(properties.get("type"), Some(&page("book")));
+                assert_eq!(
+                    properties.get("authors"),
+                    Some(&PropertyValue::List(vec![
+                        page("Jane Doe"),
+                        page("John Smith")
+                    ]))
+                );
+                assert_eq!(
+                    properties.get("status"),
+                    Some(&PropertyValue::Text("In Progress".to_string()))
+                );
+            }
+
+            #[test]
+            fn test_tasks() {
+                let content =
+                    std::fs::read_to_string("graph/pages/tests___parsing___blocks___tasks.md")
+                        .unwrap();
+                let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+                let blocks = BlockBuilder::new()
+                    .with_file_id("file".to_string())
+                    .build_all(&content, &ast)
+                    .unwrap();
+                assert_eq!(blocks[0].marker, Some(Marker::Todo));
+                assert_eq!(blocks[0].priority, Some(Priority::A));
+                assert_eq!(blocks[0].scheduled, NaiveDate::from_ymd_opt(2024, 4, 28));
+                assert_eq!(blocks[0].deadline, NaiveDate::from_ymd_opt(2024, 5, 1));
+                assert_eq!(blocks[1].marker, Some(Marker::Done));
+                assert_eq!(blocks[1].priority, None);
+                assert_eq!(blocks[2].marker, None);
+                assert_eq!(blocks[2].scheduled, None);
+
+                let json = serde_json::to_value(&blocks[0]).unwrap();
+                let mut old = json.clone();
+                old.as_object_mut().unwrap().remove("block_refs");
+                let old: Block = serde_json::from_value(old).unwrap();
+                assert!(
+                    old.block_refs.is_empty(),
+                    "Older documents have no block_refs"
+                );
+                assert_eq!(json["marker"], "TODO");
+                assert_eq!(json["priority"], "A");
+                assert_eq!(json["scheduled"], 20240428);
+                assert_eq!(json["deadline"], 20240501);
+            }
+
             #[test]
             fn test_get_properties_does_not_return_ids() {
                 let list_items = get_list_blocks_as_str();
 
                 for li in list_items {
-                    let properties = BlockBuilder::get_properties(&li);
+                    let properties = BlockBuilder::get_properties(&li, &HashSet::new());
                     assert_eq!(properties.get("id"), None);
                 }
             }
@@ -344,6 +625,44 @@ mod tests {
                 let tags = BlockBuilder::get_tags(&content);
                 assert_eq!(tags, vec!["multi word tag", "tag"]);
             }
+
+            #[test]
+            fn test_get_block_refs() {
+                let content = "((662ef9e2-4b89-4f7d-9a54-afd395b03cb0)) and \
+                    {{embed ((662effa7-a861-42df-a5bf-64c783eb8b64))}} but not (( 662ef9e2 ))";
+                let block_refs = BlockBuilder::get_block_refs(content);
+                assert_eq!(
+                    block_refs,
+                    vec![
+                        "662ef9e2-4b89-4f7d-9a54-afd395b03cb0",
+                        "662effa7-a861-42df-a5bf-64c783eb8b64"
+                    ]
+                );
+            }
+
+            #[test]
+            fn test_literals_are_not_links() {
+                let content =
+                    std::fs::read_to_string("graph/pages/tests___parsing___blocks___literals.md")
+                        .unwrap();
+                let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+                let blocks = BlockBuilder::new()
+                    .with_file_id("file".to_string())
+                    .build_all(&content, &ast)
+                    .unwrap();
+                assert_eq!(blocks.len(), 8);
+                // Code, inline code, URLs and HTML have no links or ids, only the last block does
+                let (last, literals) = blocks.split_last().unwrap();
+                for block in literals {
+                    assert!(block.tags.is_empty(), "{}", block.content);
+                    assert!(block.wikilinks.is_empty(), "{}", block.content);
+                    assert!(block.block_refs.is_empty(), "{}", block.content);
+                    assert!(block.id_span.is_none(), "{}", block.content);
+                    assert!(block.properties.is_empty(), "{}", block.content);
+                }
+                assert_eq!(last.tags, vec!["contents"]);
+                assert_eq!(last.wikilinks, vec!["contents"]);
+            }
         }
     }
 }
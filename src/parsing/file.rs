@@ -3,114 +3,197 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use chrono::NaiveDate;
 use markdown::mdast::Node;
-use regex::Regex;
+use percent_encoding::percent_decode_str;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{FileNameFormat, GraphConfig};
+use crate::parsing::block::BlockBuilder;
+use crate::parsing::journal;
+use crate::parsing::markup;
+use crate::parsing::property::{parse_properties, PropertyValue};
+use crate::parsing::reference::{references, Reference};
+use crate::parsing::span::Span;
+use crate::parsing::ID_NAMESPACE;
 
 pub struct FileBuilder {
     path: Option<PathBuf>,
-    id: String,
+    root: Option<PathBuf>,
+    config: GraphConfig,
 }
 
 impl FileBuilder {
     pub fn new() -> FileBuilder {
         FileBuilder {
             path: None,
-            id: uuid::Uuid::new_v4().to_string(),
+            root: None,
+            config: GraphConfig::default(),
         }
     }
 
+    /// The graph's config, which decides how file names become titles.
+    pub fn with_config(mut self, config: &GraphConfig) -> FileBuilder {
+        self.config = config.clone();
+        self
+    }
+
     pub fn with_path(mut self, path: PathBuf) -> FileBuilder {
         self.path = Some(path);
         self
     }
 
-    fn get_top_text(ast: &Node) -> String {
+    /// The root of the graph, which ids are relative to.
+    pub fn with_root(mut self, root: PathBuf) -> FileBuilder {
+        self.root = Some(root);
+        self
+    }
+
+    /// The path of the file relative to the graph root, with `/` separators.
+    pub fn get_relative_path(path: &Path, root: Option<&Path>) -> String {
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or(path.file_name().map(Path::new))
+            .unwrap_or(path);
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Derive the id from the graph-relative path, so re-indexing a file replaces its document.
+    fn get_id(path: &Path, root: Option<&Path>) -> String {
+        let relative = Self::get_relative_path(path, root);
+        Uuid::new_v5(&ID_NAMESPACE, relative.as_bytes()).to_string()
+    }
+
+    /// The text of the top level paragraphs, where page properties are.
+    fn get_top_text(ast: &Node) -> Result<String, String> {
         let top_text = ast
             .children()
-            .unwrap()
+            .ok_or("The markdown has no root".to_string())?
             .iter()
-            .flat_map(|paragraph| paragraph.children().unwrap())
+            .filter_map(|node| match node {
+                Node::Paragraph(paragraph) => Some(paragraph.children.iter()),
+                _ => None,
+            })
+            .flatten()
             .filter_map(|child| match child {
                 Node::Text(text) => Some(text.value.clone()),
                 _ => None,
             })
             .collect();
-        top_text
-    }
-
-    fn get_properties(top_text: &str) -> HashMap<String, String> {
-        let mut properties = HashMap::new();
-        for line in top_text.lines() {
-            let split = line.split("::").map(|s| s.to_string()); // Convert iterator over &str to iterator over String
-            if let [key, value] = split.collect::<Vec<String>>().as_slice() {
-                match key.as_str() {
-                    "title" => {}
-                    "tags" => {}
-                    _ => {
-                        properties.insert(key.trim().to_string(), value.trim().to_string());
-                    }
-                }
-            }
+        Ok(top_text)
+    }
+
+    /// Where the page properties are, if the file starts with any.
+    fn get_properties_span(&self, content: &str, ast: &Node) -> Option<Span> {
+        let first = ast.children()?.first()?;
+        let Node::Paragraph(paragraph) = first else {
+            return None;
+        };
+        let position = paragraph.position.as_ref()?;
+        let text = &content[position.start.offset..position.end.offset];
+        if self.parse_properties(text).is_empty() {
+            return None;
         }
-        properties
+        Some(Span::from_offsets(
+            content,
+            position.start.offset,
+            position.end.offset,
+        ))
+    }
+
+    fn parse_properties(&self, text: &str) -> Vec<(String, PropertyValue)> {
+        parse_properties(text, &self.config.property_separated_by_commas)
+    }
+
+    fn get_properties(&self, top_text: &str) -> HashMap<String, PropertyValue> {
+        self.parse_properties(top_text)
+            .into_iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "title" | "tags" | "alias"))
+            .collect()
+    }
+
+    /// The other names of the page, from its `alias::` property.
+    fn get_aliases(&self, top_text: &str) -> Vec<String> {
+        self.parse_properties(top_text)
+            .into_iter()
+            .filter(|(key, _)| key == "alias")
+            .flat_map(|(_, value)| value.as_strings())
+            .map(|alias| alias.trim().to_string())
+            .filter(|alias| !alias.is_empty())
+            .collect()
     }
 
     fn get_wikilinks(content: &str) -> Vec<String> {
         // [[something]] but not #[[something]]
-        let re = Regex::new(r"\s\[\[([\w\s]+)\]\]").unwrap();
-        let mut wikilinks = vec![];
-        for captures in re.captures_iter(content) {
-            assert_eq!(
-                captures.len(),
-                2,
-                "There should be the full capture and the wikilink: {:?}",
-                captures
-            );
-            wikilinks.push(captures[1].trim().to_string());
-        }
-        wikilinks
+        references(content)
+            .into_iter()
+            .filter(|reference| !reference.is_tag())
+            .map(|reference| reference.name)
+            .collect()
     }
 
-    fn get_tags(top_text: &str, content: &str) -> Vec<String> {
-        // #something or #[[something]]
-        let re = Regex::new(r"(?i)#\[\[([\w\s]+)\]\]|#(\w+)").unwrap();
+    fn get_tags(&self, top_text: &str, content: &str) -> Vec<String> {
         let mut tags = vec![];
-        for line in top_text.lines() {
-            let split = line.split("::").map(|s| s.to_string()); // Convert iterator over &str to iterator over String
-            if let [key, value] = split.collect::<Vec<String>>().as_slice() {
-                if key.as_str() == "tags" {
-                    let tags_split: Vec<&str> = value.split(',').collect();
-                    let trim_tags_split: Vec<&str> = tags_split.iter().map(|x| x.trim()).collect();
-                    tags.extend(trim_tags_split.iter().map(|x| x.to_string()));
-                }
-            }
-        }
-        for captures in re.captures_iter(content) {
-            assert_eq!(
-                captures.len(),
-                3,
-                "There should be the full capture and the tag: {:?}",
-                captures
-            );
-            if let Some(tag) = captures.get(1) {
-                tags.push(tag.as_str().to_string());
-            } else if let Some(tag) = captures.get(2) {
-                tags.push(tag.as_str().to_string());
-            } else {
-                panic!("No tag found");
+        for (key, value) in self.parse_properties(top_text) {
+            if key == "tags" {
+                tags.extend(value.as_strings());
             }
         }
+        tags.extend(
+            references(content)
+                .into_iter()
+                .filter(Reference::is_tag)
+                .map(|reference| reference.name),
+        );
         tags
     }
 
-    fn get_title(path: &Path) -> String {
+    fn get_title(path: &Path, format: FileNameFormat) -> String {
         let file_name = path
             .file_name()
             .expect("No file name")
             .to_str()
-            .expect("No file name");
-        file_name.replace(".md", "").replace("___", "/")
+            .expect("No file name")
+            .replace(".md", "");
+        match format {
+            FileNameFormat::TripleLowbar => percent_decode_str(&file_name.replace("___", "/"))
+                .decode_utf8_lossy()
+                .to_string(),
+            // Legacy graphs wrote namespaces either with dots or url encoded slashes
+            FileNameFormat::Legacy if file_name.contains('%') => percent_decode_str(&file_name)
+                .decode_utf8_lossy()
+                .to_string(),
+            FileNameFormat::Legacy => file_name.replace('.', "/"),
+        }
+    }
+
+    /// The namespace a title is in and every namespace above it, outermost first, e.g.
+    /// `a/b` and `["a", "a/b"]` for `a/b/c`. Titles with an empty part are not namespaced.
+    fn get_namespace(title: &str) -> (Option<String>, Vec<String>) {
+        let parts: Vec<&str> = title.split('/').collect();
+        if parts.len() < 2 || parts.iter().any(|part| part.trim().is_empty()) {
+            return (None, vec![]);
+        }
+        let path: Vec<String> = (1..parts.len()).map(|i| parts[..i].join("/")).collect();
+        (path.last().cloned(), path)
+    }
+
+    /// Whether the file is in the graph's journals directory.
+    fn is_journal(path: &Path, journals_directory: &str) -> bool {
+        path.parent()
+            .is_some_and(|parent| parent.ends_with(journals_directory))
+    }
+
+    /// The date of a journal file, from its name.
+    fn get_journal_day(path: &Path, file_name_format: &str) -> Option<NaiveDate> {
+        let name = path.file_stem()?.to_str()?;
+        journal::parse_file_name(name, file_name_format)
     }
 
     pub fn build(mut self, content: &str, ast: &Node) -> Result<File, String> {
@@ -120,31 +203,57 @@ impl FileBuilder {
             .ok_or("No path".to_string())?
             .to_string_lossy()
             .to_string();
-        let top_text = Self::get_top_text(ast);
-        let id = self.id;
-        let properties = Self::get_properties(&top_text);
-        let wikilinks = Self::get_wikilinks(content);
-        let tags = Self::get_tags(&top_text, content);
-        let title = Self::get_title(
-            self.path
-                .take()
-                .ok_or("No path".to_string())
-                .as_ref()
-                .unwrap(),
-        );
+        let top_text = Self::get_top_text(ast)?;
+        let id = Self::get_id(self.path.as_ref().unwrap(), self.root.as_deref());
+        let properties = self.get_properties(&top_text);
+        let aliases = self.get_aliases(&top_text);
+        let properties_span = self.get_properties_span(content, ast);
+        let prose = markup::prose(content, 0, content.len(), ast.children().map_or(&[], |c| c));
+        let wikilinks = Self::get_wikilinks(&prose);
+        let tags = self.get_tags(&top_text, &prose);
+        let block_refs = BlockBuilder::get_block_refs(&prose);
+        let file_path = self.path.take().ok_or("No path".to_string())?;
+        let config = &self.config;
+        let is_journal = Self::is_journal(&file_path, &config.journals_directory);
+        let journal_day = is_journal
+            .then(|| Self::get_journal_day(&file_path, &config.journal_file_name_format))
+            .flatten();
+        let title = match journal_day {
+            Some(day) => journal::format_title(day, &config.journal_page_title_format),
+            None => Self::get_title(&file_path, config.file_name_format),
+        };
+        // Journal titles such as `2024/04/28` are dates, not namespaces
+        let (namespace_parent, namespace_path) = if is_journal {
+            (None, vec![])
+        } else {
+            Self::get_namespace(&title)
+        };
+        let normalizer = config.normalizer();
         Ok(File {
             id,
             path,
+            normalized_title: normalizer.normalize(&title),
             title,
+            aliases,
+            namespace_parent,
+            namespace_path,
+            is_journal,
+            journal_day,
             properties,
+            properties_span,
+            normalized_wikilinks: normalizer.normalize_all(&wikilinks),
             wikilinks,
+            normalized_tags: normalizer.normalize_all(&tags),
             tags,
+            block_refs,
+            backlinks: vec![],
+            backlink_count: 0,
         })
     }
 }
 
 /// This is a markdown file in logseq
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct File {
     /// The id of the file
     pub id: String,
@@ -152,12 +261,49 @@ pub struct File {
     pub path: String,
     /// The title of the file
     pub title: String,
+    /// The title the way links are resolved, see `NameNormalizer`
+    #[serde(default)]
+    pub normalized_title: String,
+    /// Other names that links to the page can use, from its `alias::` property
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The namespace the page is in, e.g. `a/b` for `a/b/c`
+    #[serde(default)]
+    pub namespace_parent: Option<String>,
+    /// Every namespace the page is in, outermost first, e.g. `["a", "a/b"]` for `a/b/c`.
+    /// Filtering on one of them finds everything under it.
+    #[serde(default)]
+    pub namespace_path: Vec<String>,
+    /// Whether the file is in the journals directory
+    #[serde(default)]
+    pub is_journal: bool,
+    /// The date of a journal, stored as a `yyyymmdd` number
+    #[serde(default, with = "journal::day")]
+    pub journal_day: Option<NaiveDate>,
     /// The page-properties of the file
-    pub properties: HashMap<String, String>,
+    pub properties: HashMap<String, PropertyValue>,
+    /// Where the page-properties are, if there are any
+    #[serde(default)]
+    pub properties_span: Option<Span>,
     /// wikilinks in the file
     pub wikilinks: Vec<String>,
     /// page tags
     pub tags: Vec<String>,
+    /// `wikilinks`, normalized so the same page is always written the same way
+    #[serde(default)]
+    pub normalized_wikilinks: Vec<String>,
+    /// `tags`, normalized so the same page is always written the same way
+    #[serde(default)]
+    pub normalized_tags: Vec<String>,
+    /// ids of blocks referenced or embedded anywhere in the file
+    #[serde(default)]
+    pub block_refs: Vec<String>,
+    /// The titles of the pages that link to, tag or embed this one. Set when indexing.
+    #[serde(default)]
+    pub backlinks: Vec<String>,
+    /// The number of links, tags and embeds of this page on other pages
+    #[serde(default)]
+    pub backlink_count: usize,
 }
 
 #[cfg(test)]
@@ -176,6 +322,7 @@ mod tests {
                 .with_path(std::path::PathBuf::from(
                     "graph/pages/tests___parsing___files___basic.md",
                 ))
+                .with_root(std::path::PathBuf::from("graph"))
                 .build(&content, &ast)
                 .unwrap()
         }
@@ -185,15 +332,27 @@ mod tests {
             let content =
                 std::fs::read_to_string("graph/pages/tests___parsing___files___basic.md").unwrap();
             let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
-            let top_text = FileBuilder::get_top_text(&ast);
+            let top_text = FileBuilder::get_top_text(&ast).unwrap();
             assert_eq!(top_text, "tags:: foo, bar\nfoo:: bar");
         }
 
+        #[test]
+        fn test_get_properties_span() {
+            let file = get_content();
+            let span = file.properties_span.unwrap();
+            assert_eq!((span.start.line, span.start.column), (1, 1));
+            assert_eq!((span.end.line, span.end.column), (2, 10));
+            assert_eq!((span.start.offset, span.end.offset), (0, 25));
+        }
+
         #[test]
         fn test_get_properties() {
             let file = get_content();
             let properties = file.properties;
-            assert_eq!(properties.get("foo"), Some(&"bar".to_string()));
+            assert_eq!(
+                properties.get("foo"),
+                Some(&PropertyValue::Text("bar".to_string()))
+            );
             assert_eq!(properties.get("tags"), None);
         }
 
@@ -211,11 +370,180 @@ mod tests {
             assert_eq!(tags, vec!["foo", "bar", "tag", "multi word tag"]);
         }
 
+        #[test]
+        fn test_get_id() {
+            let path = std::path::Path::new("graph/pages/tests___parsing___files___basic.md");
+            let id = FileBuilder::get_id(path, Some(std::path::Path::new("graph")));
+            assert_eq!(
+                id,
+                get_content().id,
+                "The id should not change between runs"
+            );
+            assert_eq!(
+                id,
+                FileBuilder::get_id(
+                    std::path::Path::new(
+                        "/elsewhere/graph/pages/tests___parsing___files___basic.md"
+                    ),
+                    Some(std::path::Path::new("/elsewhere/graph")),
+                ),
+                "The id should only depend on the path within the graph"
+            );
+            assert_ne!(id, FileBuilder::get_id(path, None));
+        }
+
+        #[test]
+        fn test_get_relative_path() {
+            let path = std::path::Path::new("graph/pages/contents.md");
+            assert_eq!(
+                FileBuilder::get_relative_path(path, Some(std::path::Path::new("graph"))),
+                "pages/contents.md"
+            );
+            assert_eq!(
+                FileBuilder::get_relative_path(path, Some(path)),
+                "contents.md"
+            );
+        }
+
         #[test]
         fn test_get_title() {
             let file = get_content();
             let title = file.title;
             assert_eq!(title, "tests/parsing/files/basic");
+            assert!(!file.is_journal);
+            assert_eq!(file.journal_day, None);
+        }
+
+        #[test]
+        fn test_journal() {
+            let path = "graph/journals/2024_04_28.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.title, "Apr 28th, 2024");
+            assert!(file.is_journal);
+            assert_eq!(file.journal_day, NaiveDate::from_ymd_opt(2024, 4, 28));
+        }
+
+        #[test]
+        fn test_journal_with_config() {
+            let path = "graph/journals/2024_04_28.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let config = GraphConfig {
+                journal_page_title_format: "EEEE, yyyy/MM/dd".to_string(),
+                ..GraphConfig::default()
+            };
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .with_config(&config)
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.title, "Sunday, 2024/04/28");
+        }
+
+        #[test]
+        fn test_get_title_formats() {
+            let path = std::path::Path::new("pages/a___b%3F.md");
+            assert_eq!(
+                FileBuilder::get_title(path, FileNameFormat::TripleLowbar),
+                "a/b?"
+            );
+            let path = std::path::Path::new("pages/a.b.md");
+            assert_eq!(FileBuilder::get_title(path, FileNameFormat::Legacy), "a/b");
+            let path = std::path::Path::new("pages/a%2Fb.c.md");
+            assert_eq!(
+                FileBuilder::get_title(path, FileNameFormat::Legacy),
+                "a/b.c"
+            );
+        }
+
+        #[test]
+        fn test_get_aliases() {
+            let path = "tests/graphs/aliases/pages/javascript.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.aliases, vec!["JS", "ECMAScript"]);
+            assert!(!file.properties.contains_key("alias"));
+        }
+
+        #[test]
+        fn test_literals_are_not_links() {
+            let path = "graph/pages/tests___parsing___blocks___literals.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.tags, vec!["contents"]);
+            assert_eq!(file.wikilinks, vec!["contents"]);
+            assert!(file.block_refs.is_empty());
+        }
+
+        #[test]
+        fn test_top_level_nodes_without_children() {
+            // A code fence and a `---` rule at the top level have no text of their own
+            let path = "graph/pages/tests___parsing___files___top_level.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.title, "tests/parsing/files/top_level");
+            assert!(file.properties.is_empty());
+        }
+
+        #[test]
+        fn test_get_namespace() {
+            assert_eq!(
+                FileBuilder::get_namespace("tests/parsing/blocks/hierarchy"),
+                (
+                    Some("tests/parsing/blocks".to_string()),
+                    vec![
+                        "tests".to_string(),
+                        "tests/parsing".to_string(),
+                        "tests/parsing/blocks".to_string()
+                    ]
+                )
+            );
+            assert_eq!(FileBuilder::get_namespace("contents"), (None, vec![]));
+            assert_eq!(FileBuilder::get_namespace("a//b"), (None, vec![]));
+            assert_eq!(
+                FileBuilder::get_namespace("1/2"),
+                (Some("1".to_string()), vec!["1".to_string()])
+            );
+        }
+
+        #[test]
+        fn test_journal_with_unparseable_name() {
+            let path = "graph/journals/2024-04-29.md";
+            let content = std::fs::read_to_string(path).unwrap();
+            let ast = markdown::to_mdast(&content, &markdown::ParseOptions::default()).unwrap();
+            let file = FileBuilder::new()
+                .with_path(std::path::PathBuf::from(path))
+                .build(&content, &ast)
+                .unwrap();
+            assert_eq!(file.title, "2024-04-29");
+            assert!(file.is_journal);
+            assert_eq!(file.journal_day, None);
+        }
+
+        #[test]
+        fn test_documents_stored_by_older_versions_load() {
+            let json = r#"{"id": "1", "path": "a.md", "title": "a", "properties": {},
+                "wikilinks": [], "tags": []}"#;
+            let file: File = serde_json::from_str(json).unwrap();
+            assert!(!file.is_journal);
+            assert!(file.block_refs.is_empty());
         }
     }
 }
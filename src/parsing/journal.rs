@@ -0,0 +1,173 @@
+//! Journal pages, whose titles are dates.
+//!
+//! Logseq date formats use date-fns tokens such as `yyyy_MM_dd` and `MMM do, yyyy`.
+
+use chrono::{Datelike, NaiveDate};
+
+/// The default `:journal/file-name-format`
+pub const DEFAULT_FILE_NAME_FORMAT: &str = "yyyy_MM_dd";
+/// The default `:journal/page-title-format`
+pub const DEFAULT_PAGE_TITLE_FORMAT: &str = "MMM do, yyyy";
+
+/// Date-fns tokens and the chrono format they become, longest first so `MMMM` wins over `MM`.
+/// `do` has no chrono equivalent and is handled separately.
+const TOKENS: [(&str, &str); 12] = [
+    ("yyyy", "%Y"),
+    ("yy", "%y"),
+    ("MMMM", "%B"),
+    ("MMM", "%b"),
+    ("MM", "%m"),
+    ("M", "%-m"),
+    ("EEEE", "%A"),
+    ("EEE", "%a"),
+    ("EE", "%a"),
+    ("E", "%a"),
+    ("dd", "%d"),
+    ("d", "%-d"),
+];
+
+/// A piece of a date format.
+enum Part {
+    /// Text copied as is, escaped for chrono
+    Literal(String),
+    /// A chrono format specifier
+    Chrono(&'static str),
+    /// The day of the month with an ordinal suffix, e.g. `28th`
+    Ordinal,
+}
+
+fn parse_format(format: &str) -> Vec<Part> {
+    let mut parts = vec![];
+    let mut rest = format;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("do") {
+            parts.push(Part::Ordinal);
+            rest = after;
+        } else if let Some((token, chrono)) = TOKENS.iter().find(|(t, _)| rest.starts_with(t)) {
+            parts.push(Part::Chrono(chrono));
+            rest = &rest[token.len()..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            let literal = if c == '%' {
+                "%%".to_string()
+            } else {
+                c.to_string()
+            };
+            parts.push(Part::Literal(literal));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    parts
+}
+
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+/// Format a date as a journal page title.
+pub fn format_title(date: NaiveDate, format: &str) -> String {
+    parse_format(format)
+        .iter()
+        .map(|part| match part {
+            Part::Literal(literal) => date.format(literal).to_string(),
+            Part::Chrono(chrono) => date.format(chrono).to_string(),
+            Part::Ordinal => ordinal(date.day()),
+        })
+        .collect()
+}
+
+/// Parse a journal file name, without its extension.
+pub fn parse_file_name(name: &str, format: &str) -> Option<NaiveDate> {
+    let chrono: String = parse_format(format)
+        .iter()
+        .map(|part| match part {
+            Part::Literal(literal) => literal.as_str(),
+            Part::Chrono(chrono) => chrono,
+            // File names with ordinals are not supported
+            Part::Ordinal => "%-dth",
+        })
+        .collect();
+    NaiveDate::parse_from_str(name, &chrono).ok()
+}
+
+/// Serialize dates as `yyyymmdd` integers, like logseq's `journal-day`, so they can
+/// be compared and sorted in Meilisearch filters.
+pub mod day {
+    use chrono::{Datelike, NaiveDate};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn to_day(date: &NaiveDate) -> u32 {
+        date.year() as u32 * 10000 + date.month() * 100 + date.day()
+    }
+
+    pub fn from_day(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt((day / 10000) as i32, (day / 100) % 100, day % 100)
+    }
+
+    pub fn serialize<S: Serializer>(date: &Option<NaiveDate>, s: S) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => s.serialize_some(&to_day(date)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NaiveDate>, D::Error> {
+        Ok(Option::<u32>::deserialize(d)?.and_then(from_day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, 28).unwrap()
+    }
+
+    #[test]
+    fn test_format_title() {
+        assert_eq!(
+            format_title(date(), DEFAULT_PAGE_TITLE_FORMAT),
+            "Apr 28th, 2024"
+        );
+        assert_eq!(format_title(date(), "yyyy-MM-dd"), "2024-04-28");
+        assert_eq!(
+            format_title(date(), "EEEE, MMMM d, yyyy"),
+            "Sunday, April 28, 2024"
+        );
+        assert_eq!(
+            format_title(NaiveDate::from_ymd_opt(2024, 4, 11).unwrap(), "do"),
+            "11th"
+        );
+        assert_eq!(
+            format_title(NaiveDate::from_ymd_opt(2024, 4, 22).unwrap(), "do"),
+            "22nd"
+        );
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            parse_file_name("2024_04_28", DEFAULT_FILE_NAME_FORMAT),
+            Some(date())
+        );
+        assert_eq!(
+            parse_file_name("2024-04-28", DEFAULT_FILE_NAME_FORMAT),
+            None
+        );
+        assert_eq!(parse_file_name("2024-04-28", "yyyy-MM-dd"), Some(date()));
+    }
+
+    #[test]
+    fn test_day() {
+        assert_eq!(day::to_day(&date()), 20240428);
+        assert_eq!(day::from_day(20240428), Some(date()));
+    }
+}
@@ -0,0 +1,90 @@
+//! Tells prose apart from the parts of a markdown document that are taken literally.
+
+use markdown::mdast::Node;
+use regex::Regex;
+
+/// Byte ranges of code, inline code, HTML, images and link destinations under `nodes`.
+/// Logseq does not look for tags, links or references in them.
+pub fn literal_ranges(nodes: &[Node]) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    for node in nodes {
+        let Some(position) = node.position() else {
+            continue;
+        };
+        let (start, end) = (position.start.offset, position.end.offset);
+        match node {
+            Node::Code(_)
+            | Node::InlineCode(_)
+            | Node::Html(_)
+            | Node::Image(_)
+            | Node::Math(_)
+            | Node::InlineMath(_) => ranges.push((start, end)),
+            // `[label]([[page]])` links to a page
+            Node::Link(link) if link.url.starts_with("[[") => {
+                ranges.extend(literal_ranges(&link.children))
+            }
+            // Only the text of `[text](url)` is prose
+            Node::Link(link) => {
+                let text_end = link
+                    .children
+                    .last()
+                    .and_then(|child| child.position())
+                    .map(|p| p.end.offset)
+                    .unwrap_or(start);
+                ranges.push((text_end, end));
+                ranges.extend(literal_ranges(&link.children));
+            }
+            _ => ranges.extend(literal_ranges(node.children().map_or(&[], |c| c))),
+        }
+    }
+    ranges
+}
+
+/// `content[start..end]` with everything but prose blanked out with spaces, so tags, links
+/// and references can be found with the same offsets as in `content`. Besides the literal
+/// ranges of `nodes`, bare URLs are blanked out, so `page#section` is not a tag.
+pub fn prose(content: &str, start: usize, end: usize, nodes: &[Node]) -> String {
+    let url = Regex::new(r"[a-zA-Z][a-zA-Z0-9+.\-]*://\S+").unwrap();
+    let mut bytes = content.as_bytes()[start..end].to_vec();
+    let urls = url
+        .find_iter(&content[start..end])
+        .map(|m| (start + m.start(), start + m.end()));
+    for (from, to) in literal_ranges(nodes).into_iter().chain(urls) {
+        let (from, to) = (from.max(start), to.min(end));
+        if from < to {
+            bytes[from - start..to - start].fill(b' ');
+        }
+    }
+    // The ranges fall on character boundaries, so only whole characters were replaced
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prose_of(content: &str) -> String {
+        let ast = markdown::to_mdast(content, &markdown::ParseOptions::default()).unwrap();
+        prose(content, 0, content.len(), ast.children().unwrap())
+    }
+
+    #[test]
+    fn test_prose() {
+        let blank = |n: usize| " ".repeat(n);
+        assert_eq!(prose_of("a `#b` c"), format!("a {} c", blank(4)));
+        assert_eq!(
+            prose_of("[#a](https://x.y/#b)"),
+            format!("[#a{}", blank(17))
+        );
+        assert_eq!(
+            prose_of("see https://x.y/#b now"),
+            format!("see {} now", blank(14))
+        );
+        assert_eq!(prose_of("[a]([[b]])"), "[a]([[b]])");
+        assert_eq!(prose_of("é <!-- #a --> ü"), format!("é {} ü", blank(11)));
+        assert_eq!(
+            prose_of("```\n#a\n```\n\n#b"),
+            format!("{}\n\n#b", blank(10))
+        );
+    }
+}
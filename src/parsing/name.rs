@@ -0,0 +1,54 @@
+//! Page names in the form logseq compares them in.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Folds away the differences between page names that logseq ignores, so `[[Foo Bar]]`,
+/// `[[foo  bar]]` and `#[[FOO BAR]]` are the same page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameNormalizer {
+    /// Whether `[[café]]` and `[[cafe]]` are the same page too
+    pub remove_accents: bool,
+}
+
+impl NameNormalizer {
+    pub fn new(remove_accents: bool) -> NameNormalizer {
+        NameNormalizer { remove_accents }
+    }
+
+    /// Lowercase a name, trim it and collapse its whitespace, then remove its accents if asked.
+    pub fn normalize(&self, name: &str) -> String {
+        let name = name
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        if !self.remove_accents {
+            return name;
+        }
+        name.nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect()
+    }
+
+    pub fn normalize_all(&self, names: &[String]) -> Vec<String> {
+        names.iter().map(|name| self.normalize(name)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = NameNormalizer::new(false);
+        assert_eq!(normalizer.normalize(" Foo \t Bar "), "foo bar");
+        assert_eq!(normalizer.normalize("FOO BAR"), "foo bar");
+        assert_eq!(normalizer.normalize("Café"), "café");
+        let normalizer = NameNormalizer::new(true);
+        assert_eq!(normalizer.normalize("Café  Crème"), "cafe creme");
+        assert_eq!(normalizer.normalize("Ångström/Naïve"), "angstrom/naive");
+    }
+}
@@ -0,0 +1,269 @@
+//! Parsing for `key:: value` properties, shared by pages and blocks.
+
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A typed property value.
+///
+/// Values are stored untagged so they can be filtered on in Meilisearch, e.g.
+/// `properties.count > 2` or `properties.type.page = book`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Bool(bool),
+    Number(f64),
+    /// A `yyyy-mm-dd` date
+    Date(NaiveDate),
+    /// A `[[page]]`, `#page` or `#[[page]]` reference
+    PageRef {
+        page: String,
+    },
+    /// Comma separated values, or several page references
+    List(Vec<PropertyValue>),
+    Text(String),
+}
+
+impl PropertyValue {
+    /// Parse a single, already trimmed value without splitting it on commas.
+    fn parse_item(value: &str) -> PropertyValue {
+        let number = Regex::new(r"^-?\d+(\.\d+)?$").unwrap();
+        let page_ref = Regex::new(r"^(?:#?\[\[(.+)\]\]|#([^\s#\[\]]+))$").unwrap();
+        if let Ok(b) = value.parse::<bool>() {
+            PropertyValue::Bool(b)
+        } else if number.is_match(value) {
+            PropertyValue::Number(value.parse().unwrap())
+        } else if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            PropertyValue::Date(date)
+        } else if let Some(captures) = page_ref.captures(value) {
+            let page = captures.get(1).or(captures.get(2)).unwrap();
+            PropertyValue::PageRef {
+                page: page.as_str().trim().to_string(),
+            }
+        } else {
+            PropertyValue::Text(value.to_string())
+        }
+    }
+
+    /// Split a value on the commas that are not inside a `[[page]]`.
+    pub fn split_list(value: &str) -> Vec<&str> {
+        let mut items = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in value.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                ',' if depth <= 0 => {
+                    items.push(&value[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        items.push(&value[start..]);
+        items
+    }
+
+    /// Parse the text after `key::` as a single value, unless it is only page references
+    /// such as `[[a]], [[b]]`, which becomes a list of them.
+    pub fn parse(value: &str) -> PropertyValue {
+        let value = value.trim();
+        let items = Self::parse_items(value);
+        if items.len() > 1
+            && items
+                .iter()
+                .all(|item| matches!(item, PropertyValue::PageRef { .. }))
+        {
+            PropertyValue::List(items)
+        } else {
+            Self::parse_item(value)
+        }
+    }
+
+    /// Parse the text after `key::` as comma separated values. A single value is not a list.
+    pub fn parse_list(value: &str) -> PropertyValue {
+        let value = value.trim();
+        let mut items = Self::parse_items(value);
+        if items.len() > 1 {
+            PropertyValue::List(items)
+        } else {
+            items.pop().unwrap_or_else(|| Self::parse_item(value))
+        }
+    }
+
+    /// The non-empty comma separated values in `value`.
+    fn parse_items(value: &str) -> Vec<PropertyValue> {
+        Self::split_list(value)
+            .into_iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(Self::parse_item)
+            .collect()
+    }
+
+    /// The value as plain strings: list items are flattened and page refs become page names.
+    pub fn as_strings(&self) -> Vec<String> {
+        match self {
+            PropertyValue::Bool(b) => vec![b.to_string()],
+            PropertyValue::Number(n) => vec![n.to_string()],
+            PropertyValue::Date(date) => vec![date.format("%Y-%m-%d").to_string()],
+            PropertyValue::PageRef { page } => vec![page.clone()],
+            PropertyValue::List(items) => items.iter().flat_map(|i| i.as_strings()).collect(),
+            PropertyValue::Text(text) => vec![text.clone()],
+        }
+    }
+}
+
+/// Keys whose values logseq always splits on commas.
+const LIST_KEYS: &[&str] = &["tags", "alias"];
+
+/// Get every `key:: value` line in some text, in order.
+///
+/// Keys may contain letters, digits, `-` and `_`. The first line may start with a
+/// block's list marker, e.g. `- foo:: bar`. The values of `tags`, `alias` and the keys in
+/// `separated_by_commas` are comma separated lists, other values may contain commas.
+pub fn parse_properties(
+    text: &str,
+    separated_by_commas: &HashSet<String>,
+) -> Vec<(String, PropertyValue)> {
+    let re = Regex::new(r"^(?:- )?([A-Za-z0-9_][A-Za-z0-9_\-]*)::(?:\s+(.*))?$").unwrap();
+    text.lines()
+        .filter_map(|line| re.captures(line.trim()))
+        .map(|captures| {
+            let key = captures[1].to_string();
+            let value = captures.get(2).map(|v| v.as_str()).unwrap_or("");
+            let value = if LIST_KEYS.contains(&key.as_str()) || separated_by_commas.contains(&key) {
+                PropertyValue::parse_list(value)
+            } else {
+                PropertyValue::parse(value)
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(name: &str) -> PropertyValue {
+        PropertyValue::PageRef {
+            page: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PropertyValue::parse("bar"),
+            PropertyValue::Text("bar".into())
+        );
+        assert_eq!(
+            PropertyValue::parse("In Progress"),
+            PropertyValue::Text("In Progress".into())
+        );
+        assert_eq!(PropertyValue::parse("3"), PropertyValue::Number(3.0));
+        assert_eq!(PropertyValue::parse("-1.5"), PropertyValue::Number(-1.5));
+        assert_eq!(PropertyValue::parse("true"), PropertyValue::Bool(true));
+        assert_eq!(
+            PropertyValue::parse("2024-04-28"),
+            PropertyValue::Date(NaiveDate::from_ymd_opt(2024, 4, 28).unwrap())
+        );
+        assert_eq!(PropertyValue::parse("[[Jane Doe]]"), page("Jane Doe"));
+        assert_eq!(PropertyValue::parse("#book"), page("book"));
+        assert_eq!(PropertyValue::parse("#[[C++]]"), page("C++"));
+        assert_eq!(
+            PropertyValue::parse_list("[[Jane Doe]], John Smith, 3"),
+            PropertyValue::List(vec![
+                page("Jane Doe"),
+                PropertyValue::Text("John Smith".into()),
+                PropertyValue::Number(3.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let text = "- A block\ndue-date:: 2024-04-28\n  created_at:: now\nv2:: \nnot a:: property";
+        let properties = parse_properties(text, &HashSet::new());
+        assert_eq!(
+            properties,
+            vec![
+                (
+                    "due-date".to_string(),
+                    PropertyValue::Date(NaiveDate::from_ymd_opt(2024, 4, 28).unwrap())
+                ),
+                ("created_at".to_string(), PropertyValue::Text("now".into())),
+                ("v2".to_string(), PropertyValue::Text("".into())),
+            ]
+        );
+        assert_eq!(
+            parse_properties("- foo:: bar", &HashSet::new()),
+            vec![("foo".to_string(), PropertyValue::Text("bar".into()))]
+        );
+    }
+
+    #[test]
+    fn test_commas_in_text() {
+        assert_eq!(
+            PropertyValue::parse("Hello, world"),
+            PropertyValue::Text("Hello, world".into())
+        );
+        assert_eq!(
+            PropertyValue::parse("[[Jane Doe]], John Smith"),
+            PropertyValue::Text("[[Jane Doe]], John Smith".into())
+        );
+        let text = "description:: Hello, world\ntags:: a, b\nsizes:: s, m";
+        let properties = parse_properties(text, &HashSet::from(["sizes".to_string()]));
+        let list = |a: &str, b: &str| {
+            PropertyValue::List(vec![
+                PropertyValue::Text(a.into()),
+                PropertyValue::Text(b.into()),
+            ])
+        };
+        assert_eq!(
+            properties,
+            vec![
+                (
+                    "description".to_string(),
+                    PropertyValue::Text("Hello, world".into())
+                ),
+                ("tags".to_string(), list("a", "b")),
+                ("sizes".to_string(), list("s", "m")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let value = PropertyValue::List(vec![
+            page("book"),
+            PropertyValue::Number(3.0),
+            PropertyValue::Bool(false),
+            PropertyValue::Date(NaiveDate::from_ymd_opt(2024, 4, 28).unwrap()),
+            PropertyValue::Text("text".into()),
+        ]);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"[{"page":"book"},3.0,false,"2024-04-28","text"]"#);
+        assert_eq!(serde_json::from_str::<PropertyValue>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn test_commas_inside_page_refs() {
+        assert_eq!(
+            PropertyValue::parse_list("[[Smith, John]], [[Doe, Jane]]"),
+            PropertyValue::List(vec![page("Smith, John"), page("Doe, Jane")])
+        );
+    }
+
+    #[test]
+    fn test_as_strings() {
+        assert_eq!(
+            PropertyValue::parse_list("foo, [[bar]]").as_strings(),
+            vec!["foo", "bar"]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Finds the page references in some text: `[[links]]`, `#tags`, `#[[tags]]` and
+//! `[label]([[links]])`, including nested ones such as `[[a [[b]]]]`.
+
+use regex::Regex;
+
+/// How a page is referenced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// `[[page]]`
+    Link,
+    /// `#page` or `#[[page]]`
+    Tag,
+    /// `[label]([[page]])`
+    AliasLabel,
+    /// `[[page]]`, `#page` or `#[[page]]` in the value of a `key:: value` property
+    PropertyRef,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    /// The trimmed name of the page
+    pub name: String,
+    /// The byte offset of the whole reference, e.g. of the `#` in `#[[page]]`
+    pub start: usize,
+    /// The byte offset just past the reference
+    pub end: usize,
+}
+
+impl Reference {
+    pub fn is_tag(&self) -> bool {
+        self.kind == ReferenceKind::Tag
+    }
+}
+
+/// Characters that end a `#tag`, besides whitespace.
+const TAG_END: &[char] = &[',', ';', '"', '(', ')', '[', ']', '{', '}', '#'];
+
+/// Every page reference in `text`, in order. A nested reference comes after the one it
+/// is in. References do not span lines.
+pub fn references(text: &str) -> Vec<Reference> {
+    let property = Regex::new(r"^\s*(?:- )?[A-Za-z0-9_][A-Za-z0-9_\-]*::").unwrap();
+    let mut references = vec![];
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let first = references.len();
+        scan(line, offset, &mut references);
+        if let Some(key) = property.find(line) {
+            for reference in references[first..].iter_mut() {
+                if reference.start >= offset + key.end() {
+                    reference.kind = ReferenceKind::PropertyRef;
+                }
+            }
+        }
+        offset += line.len();
+    }
+    references
+}
+
+/// The offset just past the `]]` that closes the `[[` at `start`.
+fn closing(text: &str, start: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"[[") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"]]") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Add the reference to the page named `text[from..to]`, then the ones nested in its name.
+fn push(
+    text: &str,
+    offset: usize,
+    kind: ReferenceKind,
+    (start, end): (usize, usize),
+    (from, to): (usize, usize),
+    references: &mut Vec<Reference>,
+) {
+    let name = text[from..to].trim();
+    if name.is_empty() {
+        return;
+    }
+    references.push(Reference {
+        kind,
+        name: name.to_string(),
+        start: offset + start,
+        end: offset + end,
+    });
+    scan(&text[from..to], offset + from, references);
+}
+
+/// Add the references in one line, whose first byte is at `offset`.
+fn scan(line: &str, offset: usize, references: &mut Vec<Reference>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        // Only ASCII is matched, so `i` is on a character boundary whenever it is used
+        if bytes[i..].starts_with(b"[[") {
+            if let Some(end) = closing(line, i) {
+                let kind = ReferenceKind::Link;
+                push(line, offset, kind, (i, end), (i + 2, end - 2), references);
+                i = end;
+                continue;
+            }
+        } else if bytes[i] == b'#'
+            && line[..i]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace)
+        {
+            // After a space, so not `[#A]` or `C#`
+            if bytes[i + 1..].starts_with(b"[[") {
+                if let Some(end) = closing(line, i + 1) {
+                    let kind = ReferenceKind::Tag;
+                    push(line, offset, kind, (i, end), (i + 3, end - 2), references);
+                    i = end;
+                    continue;
+                }
+            } else {
+                let rest = &line[i + 1..];
+                let length = rest
+                    .find(|c: char| c.is_whitespace() || TAG_END.contains(&c))
+                    .unwrap_or(rest.len());
+                // A tag at the end of a sentence does not take its punctuation
+                let tag = rest[..length].trim_end_matches(['.', '!', '?', ':']);
+                let end = i + 1 + tag.len();
+                push(
+                    line,
+                    offset,
+                    ReferenceKind::Tag,
+                    (i, end),
+                    (i + 1, end),
+                    references,
+                );
+                i = end.max(i + 1);
+                continue;
+            }
+        } else if bytes[i] == b'[' {
+            // `[label]([[page]])`
+            let label_end = line[i..].find(']').map(|j| i + j);
+            if let Some(j) = label_end.filter(|j| bytes[*j..].starts_with(b"]([[")) {
+                if let Some(end) =
+                    closing(line, j + 2).filter(|end| bytes[*end..].starts_with(b")"))
+                {
+                    let kind = ReferenceKind::AliasLabel;
+                    push(
+                        line,
+                        offset,
+                        kind,
+                        (i, end + 1),
+                        (j + 4, end - 2),
+                        references,
+                    );
+                    i = end + 1;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<(ReferenceKind, String)> {
+        references(text)
+            .into_iter()
+            .map(|reference| (reference.kind, reference.name))
+            .collect()
+    }
+
+    #[test]
+    fn test_links() {
+        use ReferenceKind::*;
+        assert_eq!(
+            names("[[Page/With/Namespace]] [[C++]], [[2024-04-28]]"),
+            vec![
+                (Link, "Page/With/Namespace".to_string()),
+                (Link, "C++".to_string()),
+                (Link, "2024-04-28".to_string()),
+            ]
+        );
+        assert_eq!(
+            names("[[a [[b]]]] and [label]([[ Page ]])"),
+            vec![
+                (Link, "a [[b]]".to_string()),
+                (Link, "b".to_string()),
+                (AliasLabel, "Page".to_string()),
+            ]
+        );
+        assert!(names("[[]] [[unclosed [label](https://x.y)").is_empty());
+    }
+
+    #[test]
+    fn test_tags() {
+        use ReferenceKind::*;
+        assert_eq!(
+            names("#tag, #C++ #a/b. #[[multi word]] #[[a [[b]]]]"),
+            vec![
+                (Tag, "tag".to_string()),
+                (Tag, "C++".to_string()),
+                (Tag, "a/b".to_string()),
+                (Tag, "multi word".to_string()),
+                (Tag, "a [[b]]".to_string()),
+                (Link, "b".to_string()),
+            ]
+        );
+        assert!(names("[#A] C# ## heading # not").is_empty());
+    }
+
+    #[test]
+    fn test_property_refs() {
+        use ReferenceKind::*;
+        assert_eq!(
+            names("- author:: [[Jane]], #people\n  [[not a property]]"),
+            vec![
+                (PropertyRef, "Jane".to_string()),
+                (PropertyRef, "people".to_string()),
+                (Link, "not a property".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let text = "é #tag\n[x]([[a]])";
+        let spans: Vec<&str> = references(text)
+            .iter()
+            .map(|reference| &text[reference.start..reference.end])
+            .collect();
+        assert_eq!(spans, vec!["#tag", "[x]([[a]])"]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Positions in a file, so search hits and diagnostics can point at the exact place.
+
+use serde::{Deserialize, Serialize};
+
+/// A 1-indexed line and column in a file, and the 0-indexed byte offset of the same place.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Location {
+    /// Get the location of a byte offset in `content`.
+    pub fn from_offset(content: &str, offset: usize) -> Location {
+        let before = &content[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Location {
+            line,
+            column: before[line_start..].chars().count() + 1,
+            offset,
+        }
+    }
+}
+
+/// A region of a file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// Get the span between two byte offsets in `content`.
+    pub fn from_offsets(content: &str, start: usize, end: usize) -> Span {
+        Span {
+            start: Location::from_offset(content, start),
+            end: Location::from_offset(content, end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_from_offset() {
+        let content = "foo\nbär baz";
+        assert_eq!(
+            Location::from_offset(content, 9),
+            Location {
+                line: 2,
+                column: 5,
+                offset: 9
+            }
+        );
+        assert_eq!(
+            Location::from_offset(content, 0),
+            Location {
+                line: 1,
+                column: 1,
+                offset: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Parsing for task markers, priorities and `SCHEDULED`/`DEADLINE` timestamps on blocks.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The workflow state at the start of a task block, e.g. `TODO`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Marker {
+    Todo,
+    Doing,
+    Done,
+    Later,
+    Now,
+    Waiting,
+    Canceled,
+}
+
+impl Marker {
+    /// Get the marker at the start of a block, after its list marker.
+    pub fn parse(content: &str) -> Option<Marker> {
+        let re =
+            Regex::new(r"^(?:- )?(TODO|DOING|DONE|LATER|NOW|WAITING|CANCELED|CANCELLED)(?:\s|$)")
+                .unwrap();
+        let captures = re.captures(content)?;
+        match &captures[1] {
+            "TODO" => Some(Marker::Todo),
+            "DOING" => Some(Marker::Doing),
+            "DONE" => Some(Marker::Done),
+            "LATER" => Some(Marker::Later),
+            "NOW" => Some(Marker::Now),
+            "WAITING" => Some(Marker::Waiting),
+            "CANCELED" | "CANCELLED" => Some(Marker::Canceled),
+            _ => None,
+        }
+    }
+}
+
+/// A `[#A]`, `[#B]` or `[#C]` priority.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    A,
+    B,
+    C,
+}
+
+impl Priority {
+    /// Get the priority on the first line of a block.
+    pub fn parse(content: &str) -> Option<Priority> {
+        let re = Regex::new(r"\[#([ABC])\]").unwrap();
+        let first_line = content.lines().next()?;
+        match &re.captures(first_line)?[1] {
+            "A" => Some(Priority::A),
+            "B" => Some(Priority::B),
+            "C" => Some(Priority::C),
+            _ => None,
+        }
+    }
+}
+
+/// Get the date of a `SCHEDULED: <2024-04-28 Sun>` or `DEADLINE: <...>` line.
+/// Any time or repeater after the date is ignored.
+pub fn parse_timestamp(content: &str, keyword: &str) -> Option<NaiveDate> {
+    let re = Regex::new(&format!(
+        r"(?m)^\s*{}: <(\d{{4}}-\d{{2}}-\d{{2}})[^>]*>",
+        keyword
+    ))
+    .unwrap();
+    let captures = re.captures(content)?;
+    NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker() {
+        assert_eq!(Marker::parse("- TODO Write tests"), Some(Marker::Todo));
+        assert_eq!(Marker::parse("- CANCELLED Nope"), Some(Marker::Canceled));
+        assert_eq!(Marker::parse("- DONE"), Some(Marker::Done));
+        assert_eq!(Marker::parse("- TODOS are not a marker"), None);
+        assert_eq!(Marker::parse("- Mentions TODO later"), None);
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(Priority::parse("- TODO [#A] Urgent"), Some(Priority::A));
+        assert_eq!(Priority::parse("- Not a priority\n  [#B]"), None);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let content =
+            "- TODO Task\n  SCHEDULED: <2024-04-28 Sun>\n  DEADLINE: <2024-05-01 Wed 10:00 .+1w>";
+        assert_eq!(
+            parse_timestamp(content, "SCHEDULED"),
+            NaiveDate::from_ymd_opt(2024, 4, 28)
+        );
+        assert_eq!(
+            parse_timestamp(content, "DEADLINE"),
+            NaiveDate::from_ymd_opt(2024, 5, 1)
+        );
+        assert_eq!(parse_timestamp("- No dates", "SCHEDULED"), None);
+    }
+}
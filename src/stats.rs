@@ -0,0 +1,109 @@
+//! Counts of what is in a graph.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::graph::Graph;
+
+/// What a graph contains, counted without needing a search index.
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub pages: usize,
+    pub journals: usize,
+    pub blocks: usize,
+    /// Task blocks by marker, e.g. `TODO`
+    pub tasks: BTreeMap<String, usize>,
+    pub wikilinks: usize,
+    pub tags: usize,
+    pub block_refs: usize,
+    /// Distinct property keys on pages and blocks
+    pub property_keys: usize,
+    /// Groups of pages connected by links, counting pages that are linked but don't exist
+    pub components: usize,
+    /// Namespaces with pages or namespaces under them, whether or not they have a page
+    pub namespaces: usize,
+    /// The outermost of the namespaces
+    pub top_level_namespaces: usize,
+}
+
+impl Stats {
+    pub fn new(graph: &Graph) -> Stats {
+        let mut stats = Stats::default();
+        let mut property_keys = HashSet::new();
+        for page in graph.pages.iter() {
+            stats.pages += 1;
+            stats.journals += usize::from(page.file.is_journal);
+            stats.wikilinks += page.file.wikilinks.len();
+            stats.tags += page.file.tags.len();
+            stats.block_refs += page.file.block_refs.len();
+            property_keys.extend(page.file.properties.keys());
+            for block in page.blocks.iter() {
+                stats.blocks += 1;
+                property_keys.extend(block.properties.keys());
+                if let Some(marker) = block.marker {
+                    let marker = format!("{:?}", marker).to_uppercase();
+                    *stats.tasks.entry(marker).or_default() += 1;
+                }
+            }
+        }
+        stats.property_keys = property_keys.len();
+        stats.components = graph.links.components().len();
+        let roots = graph.namespaces.roots();
+        stats.top_level_namespaces = roots.len();
+        let mut stack = roots;
+        while let Some(namespace) = stack.pop() {
+            let children = graph.namespaces.children(&namespace.name);
+            stats.namespaces += usize::from(!children.is_empty());
+            stack.extend(children);
+        }
+        stats
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Pages: {} ({} journals)", self.pages, self.journals)?;
+        writeln!(f, "Blocks: {}", self.blocks)?;
+        let tasks: usize = self.tasks.values().sum();
+        let markers: Vec<String> = self
+            .tasks
+            .iter()
+            .map(|(marker, count)| format!("{} {}", count, marker))
+            .collect();
+        if markers.is_empty() {
+            writeln!(f, "Tasks: 0")?;
+        } else {
+            writeln!(f, "Tasks: {} ({})", tasks, markers.join(", "))?;
+        }
+        writeln!(f, "Links: {}", self.wikilinks)?;
+        writeln!(f, "Tags: {}", self.tags)?;
+        writeln!(f, "Block references: {}", self.block_refs)?;
+        writeln!(f, "Property keys: {}", self.property_keys)?;
+        writeln!(f, "Connected components: {}", self.components)?;
+        writeln!(
+            f,
+            "Namespaces: {} ({} top level)",
+            self.namespaces, self.top_level_namespaces
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let graph = Graph::load("graph/pages").unwrap();
+        let stats = Stats::new(&graph);
+        assert_eq!(stats.pages, 14);
+        assert_eq!(stats.journals, 0);
+        // tests, tests/lint, tests/parsing, tests/parsing/blocks and tests/parsing/files
+        assert_eq!((stats.namespaces, stats.top_level_namespaces), (5, 1));
+        assert_eq!(stats.tasks.get("TODO"), Some(&1));
+        assert_eq!(stats.tasks.get("DONE"), Some(&1));
+        let journals = Stats::new(&Graph::load("graph/journals").unwrap());
+        assert_eq!((journals.pages, journals.journals), (2, 2));
+        assert!(stats.to_string().starts_with("Pages: 14 (0 journals)\n"));
+    }
+}